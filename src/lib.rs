@@ -1,4 +1,16 @@
 #![cfg_attr(not(feature = "std"), no_std)]
+#![cfg_attr(feature = "allocator_api", feature(allocator_api))]
+
+#[cfg(all(feature = "forbid-alloc", feature = "alloc"))]
+compile_error!("the `forbid-alloc` feature can't be enabled together with `alloc` or `std`");
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+use core::mem::MaybeUninit;
+
+#[cfg(feature = "alloc")]
+use alloc::{borrow::Cow, string::String, vec::Vec};
 
 /// Write trait to use with Encoder
 pub trait Write {
@@ -6,22 +18,175 @@ pub trait Write {
 
     /// Write a single byte.
     fn write(&mut self, byte: u8) -> Result<(), Self::Error>;
+
+    /// Hint that at least `additional` more bytes are about to be written.
+    ///
+    /// Writers backed by a growable buffer can allocate room for them all at once.
+    /// The default implementation does nothing.
+    fn reserve(&mut self, additional: usize) {
+        let _ = additional;
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Write for Vec<u8> {
+    type Error = core::convert::Infallible;
+
+    fn write(&mut self, byte: u8) -> Result<(), Self::Error> {
+        self.push(byte);
+        Ok(())
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        Vec::reserve(self, additional)
+    }
+}
+
+impl<W: Write + ?Sized> Write for &mut W {
+    type Error = W::Error;
+
+    fn write(&mut self, byte: u8) -> Result<(), Self::Error> {
+        (**self).write(byte)
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        (**self).reserve(additional)
+    }
+}
+
+/// Writer that can discard what was written after some point, see [Encoder::rollback].
+pub trait TruncatableWrite: Write {
+    /// Current position, to pass to [truncate_to](Self::truncate_to) later.
+    fn mark(&self) -> usize;
+
+    /// Discard everything written after `mark`.
+    fn truncate_to(&mut self, mark: usize);
+}
+
+#[cfg(feature = "alloc")]
+impl TruncatableWrite for Vec<u8> {
+    fn mark(&self) -> usize {
+        self.len()
+    }
+
+    fn truncate_to(&mut self, mark: usize) {
+        self.truncate(mark);
+    }
+}
+
+impl<W: TruncatableWrite + ?Sized> TruncatableWrite for &mut W {
+    fn mark(&self) -> usize {
+        (**self).mark()
+    }
+
+    fn truncate_to(&mut self, mark: usize) {
+        (**self).truncate_to(mark)
+    }
+}
+
+/// Adapter to use any [`std::io::Write`] as an encoder sink.
+///
+/// This is a newtype rather than a blanket impl, because a blanket impl over
+/// `std::io::Write` would overlap with the impls for `Vec<u8>` and `&mut W`.
+///
+/// Each byte is written with `write_all`, so wrap unbuffered sinks like files or
+/// sockets in a [`std::io::BufWriter`] first.
+#[cfg(feature = "std")]
+pub struct IoWrite<T>(pub T);
+
+#[cfg(feature = "std")]
+impl<T: std::io::Write> Write for IoWrite<T> {
+    type Error = std::io::Error;
+
+    fn write(&mut self, byte: u8) -> Result<(), Self::Error> {
+        self.0.write_all(&[byte])
+    }
+}
+
+/// Adapter to use any [`acid_io::Write`] as an encoder sink.
+///
+/// Same as [IoWrite], for `no_std` projects using `acid_io` in place of `std::io`.
+/// `core2`, the other common `std::io` shim, is not supported, since all its
+/// releases are yanked.
+#[cfg(feature = "acid_io")]
+pub struct AcidIoWrite<T>(pub T);
+
+#[cfg(feature = "acid_io")]
+impl<T: acid_io::Write> Write for AcidIoWrite<T> {
+    type Error = acid_io::Error;
+
+    fn write(&mut self, byte: u8) -> Result<(), Self::Error> {
+        self.0.write_all(&[byte])
+    }
+}
+
+/// Adapter to use any [`genio::Write`] as an encoder sink.
+///
+/// Same as [IoWrite], for the `genio` IO traits. [Write::reserve] is passed on as
+/// `size_hint`.
+#[cfg(feature = "genio")]
+pub struct GenioWrite<T>(pub T);
+
+#[cfg(feature = "genio")]
+impl<T: genio::Write> Write for GenioWrite<T> {
+    type Error = T::WriteError;
+
+    fn write(&mut self, byte: u8) -> Result<(), Self::Error> {
+        self.0.write_all(&[byte])
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.0.size_hint(additional)
+    }
 }
 
 /// Streaming encoder
 ///
 /// Allows encoding of reverse-COBS messages in a streaming fashion, with almost
-/// no memory usage (internal state is just one single byte!).
+/// no memory usage (internal state is just a few bytes).
 ///
 /// To encode a message, call [write](Self::write) for each byte in the message, then call [end](Self::end).
 ///
 /// You may use the same Encoder instance to encode multiple messages. In this case, you
 /// will probably want to separate messages with a `0x00`, which you have to write manually
 /// after calling [end](Self::end), for example with `encoder.writer().write(0)`.
+///
+/// # Writer errors
+///
+/// The encoder never leaves a half-written header behind. If [write](Self::write) or
+/// [end](Self::end) returns a writer error, the byte (or the end of message) was not
+/// processed, and the encoder state is still consistent with what the writer accepted.
+/// Once the writer has recovered, you can simply retry the call that failed.
+///
+/// If you'd rather give up on the message, call [abort](Self::abort) and write a `0x00`.
+/// The receiver will see a malformed message, which it should drop, and will resync
+/// on that `0x00`.
+///
+/// # Group layout
+///
+/// How the message is split into groups, and so which header types are used, only
+/// depends on the data, and can't be chosen. In particular, the first byte of an
+/// encoded message can't be forced to be a run header: headers come after the bytes
+/// they describe, and a run header always follows at least 7 literal bytes. A
+/// receiver must not assume anything about the first byte of a message.
+///
+/// # Latency
+///
+/// The encoder doesn't buffer message bytes: non-zero bytes are written right away,
+/// and only group headers are held back, until the group is complete. The header of
+/// a complete group is written by the next [write](Self::write), [flush](Self::flush)
+/// or [end](Self::end) call, not by the write completing the group, as it was up
+/// to version 0.1.2. When streaming to a sink that forwards bytes as they come,
+/// call [flush](Self::flush) before pausing, so the header isn't held back. A run of
+/// non-zero bytes can't be cut shorter than 134 bytes, since a run header shorter
+/// than that also encodes a zero byte after the run. Receivers only decode complete
+/// messages anyway, so this doesn't delay the decoded data.
 pub struct Encoder<W> {
     w: W,
     run: u8,
     zeros: u8,
+    /// Bytes written for the current message.
+    len: usize,
 }
 
 // Standard COBS/rCOBS:
@@ -42,6 +207,7 @@ impl<W> Encoder<W> {
             w,
             run: 0,
             zeros: 0,
+            len: 0,
         }
     }
 
@@ -49,221 +215,3503 @@ impl<W> Encoder<W> {
     pub fn writer(&mut self) -> &mut W {
         &mut self.w
     }
+
+    /// Abort the current message.
+    ///
+    /// This resets the encoder state without writing anything, so the next byte
+    /// starts a new message. Whatever was already written for the aborted message
+    /// stays in the writer.
+    pub fn abort(&mut self) {
+        self.run = 0;
+        self.zeros = 0;
+        self.len = 0;
+    }
+
+    /// Number of message bytes until the current group is complete.
+    ///
+    /// A group with a zero byte is complete after 7 bytes, and a run of non-zero bytes
+    /// after 134 bytes. So if no zero was written to the current group yet, this is
+    /// only an upper bound: a zero byte completes the group at its 7th byte, or right
+    /// away if the group is already 7 bytes or longer.
+    ///
+    /// Returns 0 if the group is complete and its header not written yet, see
+    /// [flush](Self::flush).
+    pub fn bytes_until_flush(&self) -> usize {
+        if self.zeros != 0 {
+            7 - self.run as usize
+        } else {
+            134 - self.run as usize
+        }
+    }
+
+    /// Whether a message byte was written since the last [end](Self::end).
+    fn in_message(&self) -> bool {
+        self.run != 0 || self.len != 0
+    }
+
+    /// Start a message, checking at compile time that it is ended.
+    ///
+    /// This takes the encoder, and gives it back when the message is ended with
+    /// [FrameInProgress::end], so it can't be used to start another message before:
+    ///
+    /// ```compile_fail
+    /// let mut buf = [0; 16];
+    /// let frame = rzcobs::Encoder::new(rzcobs::SliceWriter::new(&mut buf)).start_frame();
+    /// let other = frame.start_frame(); // error: `FrameInProgress` has no `start_frame`
+    /// ```
+    ///
+    /// ```compile_fail
+    /// let mut buf = [0; 16];
+    /// let enc = rzcobs::Encoder::new(rzcobs::SliceWriter::new(&mut buf));
+    /// let frame = enc.start_frame();
+    /// let other = enc.start_frame(); // error: `enc` was moved into `frame`
+    /// ```
+    ///
+    /// Ending the message gives the encoder back:
+    ///
+    /// ```
+    /// let mut buf = [0; 16];
+    /// let mut frame = rzcobs::Encoder::new(rzcobs::SliceWriter::new(&mut buf)).start_frame();
+    /// frame.write_bytes(b"hello").unwrap();
+    /// let (enc, res) = frame.end();
+    /// res.unwrap();
+    /// let frame = enc.start_frame();
+    /// ```
+    ///
+    /// Panics with debug assertions enabled if a message is already in progress.
+    pub fn start_frame(self) -> FrameInProgress<W> {
+        debug_assert!(!self.in_message(), "frame already started");
+        FrameInProgress { enc: self }
+    }
 }
 
 impl<W: Write> Encoder<W> {
+    /// Reserve writer capacity for `len` more message bytes.
+    ///
+    /// This passes [max_encoded_len] of `len` to [Write::reserve], which is a no-op
+    /// for writers that don't support it.
+    pub fn reserve(&mut self, len: usize) {
+        self.w.reserve(max_encoded_len(len))
+    }
+
+    fn put(&mut self, byte: u8) -> Result<(), W::Error> {
+        self.w.write(byte)?;
+        self.len += 1;
+        Ok(())
+    }
+
     /// Write a message byte.
     pub fn write(&mut self, byte: u8) -> Result<(), W::Error> {
+        self.flush()?;
+
+        // State is only updated after the writer accepted the byte, so that
+        // the call can be retried on error.
         if self.run < 7 {
             if byte == 0 {
                 self.zeros |= 1 << self.run;
             } else {
-                self.w.write(byte)?;
+                self.put(byte)?;
             }
-
             self.run += 1;
-            if self.run == 7 && self.zeros != 0x00 {
-                self.w.write(self.zeros)?;
-                self.run = 0;
-                self.zeros = 0;
-            }
         } else if byte == 0 {
-            self.w.write((self.run - 7) | 0x80)?;
+            self.put((self.run - 7) | 0x80)?;
             self.run = 0;
             self.zeros = 0;
         } else {
-            self.w.write(byte)?;
+            self.put(byte)?;
             self.run += 1;
-            if self.run == 134 {
-                self.w.write(0xFF)?;
-                self.run = 0;
-                self.zeros = 0;
-            }
         }
         Ok(())
     }
 
+    /// Write several message bytes.
+    ///
+    /// Handy to encode the output of formatting crates like `itoa` or `ryu`, which
+    /// format into a small buffer and return it as a `&str`:
+    /// `encoder.write_bytes(itoa::Buffer::new().format(42).as_bytes())`.
+    ///
+    /// If the writer fails, the bytes before the failing one have been written.
+    pub fn write_bytes(&mut self, data: &[u8]) -> Result<(), W::Error> {
+        for &b in data {
+            self.write(b)?;
+        }
+        Ok(())
+    }
+
+    /// Write the header of the current group, if it is complete.
+    ///
+    /// Message bytes are written right away, but a group's header can only be
+    /// written once the group is complete. [write](Self::write) defers it to the
+    /// next call, so that each call does only one state change that can fail. Call
+    /// this to write it without waiting for the next byte, for example before a
+    /// pause in the stream.
+    ///
+    /// The header of an incomplete group can't be written early: it describes the
+    /// whole group, and ending the group early would make the rest of it decode as
+    /// zeros. This doesn't delay the receiver anyway, since it can't decode anything
+    /// before the end of the message.
+    pub fn flush(&mut self) -> Result<(), W::Error> {
+        let header = match self.run {
+            7 if self.zeros != 0 => self.zeros,
+            134 => 0xFF,
+            _ => return Ok(()),
+        };
+        self.put(header)?;
+        self.run = 0;
+        self.zeros = 0;
+        Ok(())
+    }
+
     /// Finish encoding a message.
     ///
     /// This does NOT write a `0x00` separator byte, you must write it yourself
     /// if you so desire.
     pub fn end(&mut self) -> Result<(), W::Error> {
+        self.end_at().map(|_| ())
+    }
+
+    /// Finish encoding a message, returning its encoded length.
+    ///
+    /// Same as [end](Self::end), but returns the number of bytes written for this
+    /// message, which is where the `0x00` separator goes when writing into a buffer
+    /// starting at the beginning of the message. Bytes written directly to
+    /// [writer](Self::writer) are not counted.
+    pub fn end_at(&mut self) -> Result<usize, W::Error> {
+        self.flush()?;
         match self.run {
             0 => {}
-            1..=6 => self.w.write((self.zeros | (0xFF << self.run)) & 0x7F)?,
-            _ => self.w.write((self.run - 7) | 0x80)?,
+            1..=6 => self.put((self.zeros | (0xFF << self.run)) & 0x7F)?,
+            _ => self.put((self.run - 7) | 0x80)?,
         }
+        let len = self.len;
         self.run = 0;
         self.zeros = 0;
-        Ok(())
+        self.len = 0;
+        Ok(len)
     }
-}
 
-/// Encode a full message.
-///
-/// Encodes a single message and returns it as a `Vec`. The returned data does
-/// not include any `0x00` separator byte, you have to add it yourself.
-///
-/// This is a convenience function using [Encoder] internally. For streaming encoding, use [Encoder].
-#[cfg(feature = "std")]
-pub fn encode(data: &[u8]) -> Vec<u8> {
-    struct VecWriter<'a>(&'a mut Vec<u8>);
-
-    impl<'a> Write for VecWriter<'a> {
-        type Error = std::convert::Infallible;
-        fn write(&mut self, byte: u8) -> Result<(), Self::Error> {
-            self.0.push(byte);
-            Ok(())
+    /// Finish a message with an already encoded tail.
+    ///
+    /// `encoded` is the encoding of the rest of the message, for example a constant
+    /// tail encoded once with [const_encode]. It is written as is, and the message
+    /// is finished, like with [end](Self::end).
+    ///
+    /// Encoded fragments can't be spliced anywhere: each group's header describes the
+    /// bytes before it, so this is only possible at a group boundary, once the
+    /// current group is complete: at the start of a message, right after 7 bytes
+    /// including a zero, or right after the zero byte ending a run of 7 or more
+    /// non-zero bytes. Calling it anywhere else, or with invalid `encoded` data, is
+    /// a logic error producing a corrupt message, and panics with debug assertions
+    /// enabled.
+    ///
+    /// If the writer fails, part of `encoded` may have been written. Call
+    /// [abort](Self::abort) and give up on the message.
+    pub fn write_precoded_suffix(&mut self, encoded: &[u8]) -> Result<(), W::Error> {
+        self.flush()?;
+        debug_assert!(self.run == 0, "not at a group boundary");
+        debug_assert!(
+            !encoded.contains(&0) && decoded_len(encoded).is_ok(),
+            "invalid encoded suffix"
+        );
+        for &b in encoded {
+            self.put(b)?;
         }
+        self.run = 0;
+        self.zeros = 0;
+        self.len = 0;
+        Ok(())
     }
 
-    let mut res = Vec::new();
-    let mut enc = Encoder::new(VecWriter(&mut res));
-    for &b in data {
-        enc.write(b).unwrap();
+    /// Start a tagged message, writing `tag` as its first byte.
+    ///
+    /// The tag is encoded like any other message byte, and decodes back as the
+    /// first byte of the message. Write the payload with [write](Self::write), then
+    /// finish with [end_frame](Self::end_frame).
+    ///
+    /// Panics with debug assertions enabled if a message is already in progress.
+    pub fn begin_frame(&mut self, tag: u8) -> Result<(), W::Error> {
+        debug_assert!(!self.in_message(), "frame already started");
+        self.write(tag)
+    }
+
+    /// Finish a message started with [begin_frame](Self::begin_frame).
+    ///
+    /// Same as [end](Self::end). Panics with debug assertions enabled if no message
+    /// was started.
+    pub fn end_frame(&mut self) -> Result<(), W::Error> {
+        debug_assert!(self.in_message(), "frame not started");
+        self.end()
     }
-    enc.end().unwrap();
-    res
 }
 
-/// Error indicating the decoded data was malformed reverse-COBS.
-#[cfg(feature = "std")]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct MalformedError;
+impl<W: TruncatableWrite> Encoder<W> {
+    /// Cancel the current message, discarding what was written for it.
+    ///
+    /// Like [abort](Self::abort), but also truncates the writer back to where the
+    /// message started, so the next message can take its place, for example to
+    /// let a higher priority message preempt it.
+    ///
+    /// Bytes written directly to [writer](Self::writer) since the message started
+    /// are not known to the encoder, so they are not discarded and shift the point
+    /// the writer is truncated to. Only write to it between messages.
+    pub fn rollback(&mut self) {
+        let mark = self.w.mark() - self.len;
+        self.w.truncate_to(mark);
+        self.abort();
+    }
+}
 
-/// Decode a full message.
+/// Allows formatting text straight into a message with `write!`.
 ///
-/// `data` must be a full rzCOBS encoded message. Decoding partial
-/// messages is not possible. `data` must NOT include any `0x00` separator byte.
-#[cfg(feature = "std")]
-pub fn decode(data: &[u8]) -> Result<Vec<u8>, MalformedError> {
-    let mut res = vec![];
-    decode_helper(data, &mut res).map_err(|_| MalformedError)?;
-    Ok(res)
+/// The writer error is lost, since `core::fmt` errors carry no data.
+impl<W: Write> core::fmt::Write for Encoder<W> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.write_bytes(s.as_bytes()).map_err(|_| core::fmt::Error)
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum DecodeError {
-    MalformedError,
-    BufferOverflow,
+/// Message being encoded, see [Encoder::start_frame].
+#[must_use = "the message must be ended with `end`"]
+pub struct FrameInProgress<W> {
+    enc: Encoder<W>,
 }
 
-/// Collections that allow pushing u8 and reversing the element order in place
-trait Buffer {
-    fn try_push(&mut self, x: u8) -> Result<(), DecodeError>;
-    fn rev(&mut self);
+impl<W> FrameInProgress<W> {
+    /// Mutably borrow the inner writer.
+    pub fn writer(&mut self) -> &mut W {
+        self.enc.writer()
+    }
 }
 
-#[cfg(feature = "std")]
-impl Buffer for Vec<u8> {
-    fn try_push(&mut self, x: u8) -> Result<(), DecodeError> {
-        Vec::<u8>::push(self, x);
+impl<W: Write> FrameInProgress<W> {
+    /// Write a message byte, see [Encoder::write].
+    pub fn write(&mut self, byte: u8) -> Result<(), W::Error> {
+        self.enc.write(byte)
+    }
 
-        Ok(())
+    /// Write several message bytes, see [Encoder::write_bytes].
+    pub fn write_bytes(&mut self, data: &[u8]) -> Result<(), W::Error> {
+        self.enc.write_bytes(data)
     }
 
-    fn rev(&mut self) {
-        self.reverse();
+    /// End the message, see [Encoder::end], giving the encoder back.
+    ///
+    /// The encoder is given back even if the writer fails. The message is then
+    /// aborted, see [Encoder::abort].
+    pub fn end(mut self) -> (Encoder<W>, Result<(), W::Error>) {
+        let res = self.enc.end();
+        if res.is_err() {
+            self.enc.abort();
+        }
+        (self.enc, res)
     }
 }
 
-/// Decode a full message.
+/// Encoder for a stream of `0x00`-separated messages, such as logs over RTT.
 ///
-/// `data` must be a full rzCOBS encoded message. Decoding partial
-/// messages is not possible. `data` must NOT include any `0x00` separator byte.
-pub fn decode_to_slice<'a>(data: &[u8], res: &'a mut [u8]) -> Result<&'a mut [u8], DecodeError> {
-    struct Vec<'a> {
-        data: &'a mut [u8],
-        len: usize,
+/// [log_frame](Self::log_frame) encodes a whole message and writes its `0x00`
+/// separator, so each message is one call.
+///
+/// # Writer errors
+///
+/// A writer that can fail mid-message, like a non-blocking channel returning
+/// `WouldBlock` when full, leaves a partial message behind. The framer then starts
+/// the next message with an extra `0x00`, so that the receiver sees the partial
+/// message on its own and resyncs right after it. Beware the receiver has no way
+/// to tell a partial message from a complete one: it may decode fine to the wrong
+/// data. To avoid this, either make sure the writer has room for the whole message
+/// before calling [log_frame](Self::log_frame), that is [max_encoded_len] plus one
+/// byte, or write into a buffer and send it as a whole.
+pub struct Framer<W> {
+    enc: Encoder<W>,
+    /// The last message was not fully written, so it is missing its separator.
+    broken: bool,
+}
+
+impl<W> Framer<W> {
+    /// Create a new framer with the given writer.
+    pub const fn new(w: W) -> Self {
+        Self {
+            enc: Encoder::new(w),
+            broken: false,
+        }
     }
 
-    impl<'a> Buffer for Vec<'a> {
-        fn try_push(&mut self, x: u8) -> Result<(), DecodeError> {
-            *self
-                .data
-                .get_mut(self.len)
-                .ok_or(DecodeError::BufferOverflow)? = x;
-            self.len += 1;
+    /// Mutably borrow the inner writer.
+    pub fn writer(&mut self) -> &mut W {
+        self.enc.writer()
+    }
+}
 
-            Ok(())
+impl<W: Write> Framer<W> {
+    /// Write a full message, followed by a `0x00` separator byte.
+    ///
+    /// On error, the message is dropped, see the [writer errors](Self#writer-errors)
+    /// section.
+    pub fn log_frame(&mut self, data: &[u8]) -> Result<(), W::Error> {
+        if self.broken {
+            self.enc.writer().write(0)?;
+            self.broken = false;
         }
 
-        fn rev(&mut self) {
-            self.data[..self.len].reverse()
+        let res = self.write_frame(data);
+        if res.is_err() {
+            self.enc.abort();
+            self.broken = true;
         }
+        res
     }
 
-    let mut res = Vec { data: res, len: 0 };
-    decode_helper(data, &mut res)?;
+    fn write_frame(&mut self, data: &[u8]) -> Result<(), W::Error> {
+        self.enc.write_bytes(data)?;
+        self.enc.end()?;
+        self.enc.writer().write(0)
+    }
+}
 
-    Ok(&mut res.data[..res.len])
+/// Encoder for messages prefixed with their length, to get them back exactly.
+///
+/// Decoding an rzCOBS message gives up to 6 extra trailing zeros, since padding
+/// can't be told apart from zeros in the message. This prepends the message length
+/// as an unsigned LEB128 varint before encoding, so that [LengthFramedDecoder] can
+/// strip the padding. This costs 1 byte for messages up to 127 bytes, 2 bytes up to
+/// 16383 bytes, and so on, before encoding.
+///
+/// Each message is followed by a `0x00` separator byte.
+pub struct LengthFramedEncoder<W> {
+    enc: Encoder<W>,
 }
 
-fn decode_helper(data: &[u8], dst: &mut impl Buffer) -> Result<(), DecodeError> {
-    let mut data = data.iter().rev().cloned();
-    while let Some(x) = data.next() {
-        match x {
-            0 => return Err(DecodeError::MalformedError),
-            0x01..=0x7f => {
-                for i in 0..7 {
-                    if x & (1 << (6 - i)) == 0 {
-                        dst.try_push(data.next().ok_or(DecodeError::MalformedError)?)?;
-                    } else {
-                        dst.try_push(0)?;
-                    }
-                }
-            }
-            0x80..=0xfe => {
-                let n = (x & 0x7f) + 7;
-                dst.try_push(0)?;
-                for _ in 0..n {
-                    dst.try_push(data.next().ok_or(DecodeError::MalformedError)?)?;
-                }
-            }
-            0xff => {
-                for _ in 0..134 {
-                    dst.try_push(data.next().ok_or(DecodeError::MalformedError)?)?;
-                }
-            }
+impl<W> LengthFramedEncoder<W> {
+    /// Create a new encoder with the given writer.
+    pub const fn new(w: W) -> Self {
+        Self {
+            enc: Encoder::new(w),
         }
     }
 
-    dst.rev();
-    Ok(())
+    /// Mutably borrow the inner writer.
+    pub fn writer(&mut self) -> &mut W {
+        self.enc.writer()
+    }
 }
 
-#[cfg(feature = "std")]
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use hex_literal::hex;
+impl<W: Write> LengthFramedEncoder<W> {
+    /// Write a full message with its length, followed by a `0x00` separator byte.
+    ///
+    /// On error, the message is dropped. Whatever was already written of it stays in
+    /// the writer.
+    pub fn write_frame(&mut self, data: &[u8]) -> Result<(), W::Error> {
+        let res = self.try_write_frame(data);
+        if res.is_err() {
+            self.enc.abort();
+        }
+        res
+    }
 
-    #[test]
-    fn it_works() {
-        let tests: &[(&[u8], &[u8])] = &[
-            (
-                &hex!(""),
-                &hex!(""),
-            ), (
-                &hex!("00"),
-                &hex!("7f"),
-            ), (
-                &hex!("0000"),
-                &hex!("7f"),
-            ), (
-                &hex!("00000000000000"),
-                &hex!("7f"),
-            ), (
-                &hex!("0000000000000000"),
-                &hex!("7f7f"),
-            ), (
-                &hex!("01"),
-                &hex!("017e"),
-            ), (
-                &hex!("0100"),
-                &hex!("017e"),
-            ), (
-                &hex!("0001"),
+    fn try_write_frame(&mut self, data: &[u8]) -> Result<(), W::Error> {
+        for b in varint(data.len()) {
+            self.enc.write(b)?;
+        }
+        self.enc.write_bytes(data)?;
+        self.enc.end()?;
+        self.enc.writer().write(0)
+    }
+}
+
+/// Decoder for messages written by [LengthFramedEncoder].
+///
+/// Messages are decoded into the buffer given on creation, which must have room for
+/// the decoded message with its length prefix and padding. The buffer is reused for
+/// each message.
+pub struct LengthFramedDecoder<'a> {
+    buf: &'a mut [u8],
+}
+
+impl<'a> LengthFramedDecoder<'a> {
+    /// Create a new decoder using `buf` for decoding.
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf }
+    }
+
+    /// Decode a full message, without its `0x00` separator byte.
+    ///
+    /// Returns exactly the original message. Fails with [DecodeError::MalformedError]
+    /// if the length prefix is invalid, or [DecodeError::LengthMismatch] if it doesn't
+    /// match the decoded message.
+    pub fn decode(&mut self, data: &[u8]) -> Result<&[u8], DecodeError> {
+        let res = decode_to_slice(data, self.buf)?;
+        let (len, n) = read_varint(res).ok_or(DecodeError::MalformedError)?;
+        let rest = &res[n..];
+        if len > rest.len() || rest.len() - len > padding_len(data) {
+            return Err(DecodeError::LengthMismatch);
+        }
+        Ok(&rest[..len])
+    }
+}
+
+/// Writer splitting the encoded byte stream into fixed-size chunks.
+///
+/// Bytes are collected in an internal buffer of `N` bytes. Each time it fills up,
+/// the callback is called with the full chunk. Call [flush](Self::flush) after
+/// [Encoder::end] to deliver the last, possibly shorter, chunk.
+///
+/// The chunks are just pieces of the encoded stream, they are not decodable
+/// on their own. The receiver must concatenate all chunks of a message before
+/// decoding it.
+///
+/// If the callback fails, the chunk is kept and the byte that completed it is not
+/// consumed, so writing it again (or retrying the [Encoder] call) retries the delivery.
+pub struct ChunkWriter<F, const N: usize> {
+    f: F,
+    buf: [u8; N],
+    len: usize,
+}
+
+impl<F, const N: usize> ChunkWriter<F, N> {
+    /// Create a new chunk writer calling `f` for each chunk.
+    ///
+    /// Panics if `N` is zero.
+    pub const fn new(f: F) -> Self {
+        assert!(N != 0, "chunk size must be non-zero");
+        Self {
+            f,
+            buf: [0; N],
+            len: 0,
+        }
+    }
+
+    /// Number of bytes buffered, not yet delivered to the callback.
+    pub fn pending(&self) -> usize {
+        self.len
+    }
+}
+
+impl<F, E, const N: usize> ChunkWriter<F, N>
+where
+    F: FnMut(&[u8]) -> Result<(), E>,
+{
+    /// Deliver the buffered bytes to the callback, if any.
+    pub fn flush(&mut self) -> Result<(), E> {
+        if self.len != 0 {
+            (self.f)(&self.buf[..self.len])?;
+            self.len = 0;
+        }
+        Ok(())
+    }
+}
+
+impl<F, E, const N: usize> Write for ChunkWriter<F, N>
+where
+    F: FnMut(&[u8]) -> Result<(), E>,
+{
+    type Error = E;
+
+    fn write(&mut self, byte: u8) -> Result<(), Self::Error> {
+        // A full buffer is always flushed, or the byte filling it is given back.
+        debug_assert!(self.len < N);
+        self.buf[self.len] = byte;
+        self.len += 1;
+        if self.len == N {
+            if let Err(e) = self.flush() {
+                self.len -= 1;
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Packet header flag of the first packet of a message, see [ChunkedFrameWriter].
+const PACKET_FIRST: u8 = 0x40;
+/// Packet header flag of the last packet of a message, see [ChunkedFrameWriter].
+const PACKET_LAST: u8 = 0x80;
+
+/// Packet header for packet `index` of a message, without the last packet flag.
+///
+/// The first packet has the first packet flag, the next ones a sequence number
+/// counting from 1 to 63 then wrapping back to 1, so headers are never `0x00`.
+fn packet_header(index: usize) -> u8 {
+    match index {
+        0 => PACKET_FIRST,
+        _ => ((index - 1) % 63 + 1) as u8,
+    }
+}
+
+/// Writer splitting each message into transport packets of up to `N` bytes.
+///
+/// Unlike [ChunkWriter], each packet starts with a one byte header, followed by up
+/// to `N - 1` bytes of the encoded message. The header tells the first and last
+/// packets of a message apart, and numbers the others, so a receiver can find
+/// missing packets. It is never `0x00`. [FrameReassembler] puts the messages back
+/// together, which must be done before decoding them.
+///
+/// A message ends when its `0x00` separator is written, which is not sent: it must
+/// be written after each message, for example with [Framer]. A message's last
+/// packet is sent with the separator, so it's never empty unless the message is.
+///
+/// If the callback fails, the packet is kept and the byte that completed it is not
+/// consumed, so writing it again (or retrying the [Encoder] call) retries the delivery.
+pub struct ChunkedFrameWriter<F, const N: usize> {
+    f: F,
+    buf: [u8; N],
+    /// Bytes in `buf`, including the header byte.
+    len: usize,
+    /// Index of the current packet in the message.
+    index: usize,
+}
+
+impl<F, const N: usize> ChunkedFrameWriter<F, N> {
+    /// Create a new writer calling `f` for each packet.
+    ///
+    /// Panics if `N` is less than 2.
+    pub const fn new(f: F) -> Self {
+        assert!(N >= 2, "packet size must be at least 2");
+        Self {
+            f,
+            buf: [0; N],
+            len: 1,
+            index: 0,
+        }
+    }
+}
+
+impl<F, E, const N: usize> Write for ChunkedFrameWriter<F, N>
+where
+    F: FnMut(&[u8]) -> Result<(), E>,
+{
+    type Error = E;
+
+    fn write(&mut self, byte: u8) -> Result<(), Self::Error> {
+        if byte == 0 {
+            self.buf[0] = packet_header(self.index) | PACKET_LAST;
+            (self.f)(&self.buf[..self.len])?;
+            self.len = 1;
+            self.index = 0;
+            return Ok(());
+        }
+
+        if self.len == N {
+            self.buf[0] = packet_header(self.index);
+            (self.f)(&self.buf)?;
+            self.len = 1;
+            self.index += 1;
+        }
+        self.buf[self.len] = byte;
+        self.len += 1;
+        Ok(())
+    }
+}
+
+/// Receiver putting back together messages split by [ChunkedFrameWriter].
+///
+/// Returns the encoded messages, without separator, ready to decode. A message with
+/// a missing packet is discarded, reported with [DecodeError::PacketLost] when the
+/// gap is seen. If the last packets of a message are lost, it is discarded silently
+/// when the next message starts.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone)]
+pub struct FrameReassembler {
+    buf: Vec<u8>,
+    max_frame_bytes: usize,
+    /// Index of the next packet expected, or `None` outside of a message.
+    next: Option<usize>,
+}
+
+#[cfg(feature = "alloc")]
+impl FrameReassembler {
+    /// Create a receiver for messages of up to `max_frame_bytes` encoded bytes.
+    pub const fn new(max_frame_bytes: usize) -> Self {
+        Self {
+            buf: Vec::new(),
+            max_frame_bytes,
+            next: None,
+        }
+    }
+
+    /// Process a received packet.
+    ///
+    /// Returns the encoded message when `packet` is its last one. If the message
+    /// gets longer than the maximum, returns [DecodeError::FrameTooLong] and
+    /// discards the rest of it.
+    pub fn push(&mut self, packet: &[u8]) -> Option<Result<Vec<u8>, DecodeError>> {
+        let (&header, data) = match packet.split_first() {
+            Some(x) => x,
+            None => return Some(Err(DecodeError::MalformedError)),
+        };
+        if header & PACKET_FIRST != 0 {
+            self.buf.clear();
+            self.next = Some(0);
+        }
+        let index = self.next?;
+
+        if header & !PACKET_LAST != packet_header(index) {
+            self.buf.clear();
+            self.next = None;
+            return Some(Err(DecodeError::PacketLost));
+        }
+        if self.buf.len() + data.len() > self.max_frame_bytes {
+            self.buf.clear();
+            self.next = None;
+            return Some(Err(DecodeError::FrameTooLong));
+        }
+        self.buf.extend_from_slice(data);
+
+        if header & PACKET_LAST != 0 {
+            self.next = None;
+            return Some(Ok(core::mem::take(&mut self.buf)));
+        }
+        self.next = Some(index + 1);
+        None
+    }
+}
+
+/// Writer transforming each byte before forwarding it to an inner writer.
+///
+/// This is useful for whitening or obfuscating the encoded stream. The receiver
+/// must apply the inverse transform before decoding.
+///
+/// The encoded stream never contains `0x00`, which is what makes `0x00` usable as a
+/// message separator. If the transformed stream is still delimited by `0x00`, the
+/// transform must map no byte to `0x00`. Otherwise, transform the stream after adding
+/// the separators, and undo it before splitting messages.
+pub struct MapWriter<W, F> {
+    w: W,
+    f: F,
+}
+
+impl<W, F> MapWriter<W, F> {
+    /// Create a new writer applying `f` to each byte before writing it to `w`.
+    pub const fn new(w: W, f: F) -> Self {
+        Self { w, f }
+    }
+
+    /// Mutably borrow the inner writer.
+    pub fn writer(&mut self) -> &mut W {
+        &mut self.w
+    }
+}
+
+impl<W: Write, F: FnMut(u8) -> u8> Write for MapWriter<W, F> {
+    type Error = W::Error;
+
+    fn write(&mut self, byte: u8) -> Result<(), Self::Error> {
+        self.w.write((self.f)(byte))
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.w.reserve(additional)
+    }
+}
+
+/// Counters kept by [StatsWriter].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WriterStats {
+    /// Bytes written, including separators.
+    pub bytes: usize,
+    /// `0x00` separator bytes written, that is, complete messages when each message
+    /// is followed by a separator.
+    pub frames: usize,
+}
+
+/// Writer counting the bytes and messages written through it, for telemetry.
+///
+/// The encoded stream never contains `0x00`, so each `0x00` written is counted as
+/// the end of a message. Bytes rejected by the inner writer are not counted.
+/// Counters wrap around on overflow, which can happen on 32-bit targets for
+/// long-running streams.
+pub struct StatsWriter<W> {
+    w: W,
+    stats: WriterStats,
+}
+
+impl<W> StatsWriter<W> {
+    /// Create a new writer counting what is written to `w`.
+    pub const fn new(w: W) -> Self {
+        Self {
+            w,
+            stats: WriterStats {
+                bytes: 0,
+                frames: 0,
+            },
+        }
+    }
+
+    /// Mutably borrow the inner writer.
+    pub fn writer(&mut self) -> &mut W {
+        &mut self.w
+    }
+
+    /// Counters since the writer was created.
+    pub fn stats(&self) -> WriterStats {
+        self.stats
+    }
+}
+
+impl<W: Write> Write for StatsWriter<W> {
+    type Error = W::Error;
+
+    fn write(&mut self, byte: u8) -> Result<(), Self::Error> {
+        self.w.write(byte)?;
+        self.stats.bytes = self.stats.bytes.wrapping_add(1);
+        if byte == 0 {
+            self.stats.frames = self.stats.frames.wrapping_add(1);
+        }
+        Ok(())
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.w.reserve(additional)
+    }
+}
+
+/// CRC algorithm for [CrcTeeWriter].
+pub trait Crc {
+    /// Type of the CRC register and of the CRC.
+    type Value: Copy;
+    /// Register value before any byte.
+    const INIT: Self::Value;
+    /// Add a byte to the register.
+    fn update(crc: Self::Value, byte: u8) -> Self::Value;
+    /// Compute the CRC from the register.
+    fn finish(crc: Self::Value) -> Self::Value;
+}
+
+/// The common CRC-32, ISO-HDLC, as used by Ethernet and zlib.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Crc32;
+
+impl Crc for Crc32 {
+    type Value = u32;
+    const INIT: u32 = 0xffff_ffff;
+
+    fn update(crc: u32, byte: u8) -> u32 {
+        CRC32_TABLE[(crc as u8 ^ byte) as usize] ^ (crc >> 8)
+    }
+
+    fn finish(crc: u32) -> u32 {
+        !crc
+    }
+}
+
+/// CRC-16/IBM-3740, also known as CRC-16/CCITT-FALSE, as used by many serial links.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Crc16;
+
+impl Crc for Crc16 {
+    type Value = u16;
+    const INIT: u16 = 0xffff;
+
+    fn update(crc: u16, byte: u8) -> u16 {
+        CRC16_TABLE[((crc >> 8) as u8 ^ byte) as usize] ^ (crc << 8)
+    }
+
+    fn finish(crc: u16) -> u16 {
+        crc
+    }
+}
+
+/// Writer computing a CRC of the bytes written through it.
+///
+/// The CRC is of the encoded bytes, with [Crc32] by default, or any [Crc] with
+/// [with_crc](Self::with_crc). It covers everything written since the writer was
+/// created or [reset](Self::reset), so reset it between messages. Bytes rejected by
+/// the inner writer are not counted.
+///
+/// The CRC bytes may contain `0x00`, so they can't be sent as is after the encoded
+/// message: encode them too, for example as a message of their own.
+pub struct CrcTeeWriter<W, C: Crc = Crc32> {
+    w: W,
+    crc: C::Value,
+}
+
+const CRC16_TABLE: [u16; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u16) << 8;
+        let mut k = 0;
+        while k < 8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+            k += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+            k += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+impl<W> CrcTeeWriter<W> {
+    /// Create a new writer computing the [Crc32] of what is written to `w`.
+    pub const fn new(w: W) -> Self {
+        Self {
+            w,
+            crc: Crc32::INIT,
+        }
+    }
+}
+
+impl<W, C: Crc> CrcTeeWriter<W, C> {
+    /// Create a new writer computing the CRC of what is written to `w`, with `crc`.
+    pub fn with_crc(w: W, crc: C) -> Self {
+        let _ = crc;
+        Self { w, crc: C::INIT }
+    }
+
+    /// Mutably borrow the inner writer.
+    pub fn writer(&mut self) -> &mut W {
+        &mut self.w
+    }
+
+    /// CRC of the bytes written since the writer was created or reset.
+    pub fn crc(&self) -> C::Value {
+        C::finish(self.crc)
+    }
+
+    /// Restart the CRC, for example at the start of a message.
+    pub fn reset(&mut self) {
+        self.crc = C::INIT;
+    }
+}
+
+impl<W: Write, C: Crc> Write for CrcTeeWriter<W, C> {
+    type Error = W::Error;
+
+    fn write(&mut self, byte: u8) -> Result<(), Self::Error> {
+        self.w.write(byte)?;
+        self.crc = C::update(self.crc, byte);
+        Ok(())
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.w.reserve(additional)
+    }
+}
+
+/// Writer sending the encoded stream to a channel, one message at a time.
+///
+/// Bytes are collected in a `Vec`, which is sent each time a `0x00` separator byte
+/// is written, with the separator as its last byte. Call [flush](Self::flush) to
+/// send bytes written since the last separator, if any.
+///
+/// Fails with [ChannelClosedError] if the receiver is gone. The failed byte is not
+/// consumed, so the bytes of the message are kept until it is retried.
+#[cfg(feature = "std")]
+pub struct SenderWriter {
+    tx: std::sync::mpsc::Sender<Vec<u8>>,
+    buf: Vec<u8>,
+}
+
+/// Error indicating the receiving end of a channel was dropped.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelClosedError;
+
+#[cfg(feature = "std")]
+impl SenderWriter {
+    /// Create a new writer sending to `tx`.
+    pub fn new(tx: std::sync::mpsc::Sender<Vec<u8>>) -> Self {
+        Self {
+            tx,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Send the bytes written since the last separator, if any.
+    pub fn flush(&mut self) -> Result<(), ChannelClosedError> {
+        if !self.buf.is_empty() {
+            self.send()?;
+        }
+        Ok(())
+    }
+
+    fn send(&mut self) -> Result<(), ChannelClosedError> {
+        let buf = core::mem::take(&mut self.buf);
+        self.tx.send(buf).map_err(|e| {
+            self.buf = e.0;
+            ChannelClosedError
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl Write for SenderWriter {
+    type Error = ChannelClosedError;
+
+    fn write(&mut self, byte: u8) -> Result<(), Self::Error> {
+        self.buf.push(byte);
+        if byte == 0 {
+            if let Err(e) = self.send() {
+                self.buf.pop();
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.buf.reserve(additional)
+    }
+}
+
+/// Writer enforcing a maximum encoded message length.
+///
+/// Writing a byte past the limit fails with [LimitError::LimitExceeded], so an
+/// [Encoder] fails as soon as the message gets too long, instead of at the end.
+/// See [Encoder::with_max_encoded].
+///
+/// Writing a `0x00` separator byte is always allowed, and starts a new message
+/// with a fresh limit. If you separate messages some other way, call
+/// [reset](Self::reset) between messages.
+pub struct LimitWriter<W> {
+    w: W,
+    max: usize,
+    len: usize,
+}
+
+/// Error returned by [LimitWriter].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitError<E> {
+    /// The inner writer failed.
+    Writer(E),
+    /// The encoded message would be longer than the limit.
+    LimitExceeded,
+}
+
+impl<W> LimitWriter<W> {
+    /// Create a new writer allowing messages of up to `max` bytes.
+    pub const fn new(w: W, max: usize) -> Self {
+        Self { w, max, len: 0 }
+    }
+
+    /// Mutably borrow the inner writer.
+    pub fn writer(&mut self) -> &mut W {
+        &mut self.w
+    }
+
+    /// Start a new message with a fresh limit.
+    pub fn reset(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl<W: Write> Write for LimitWriter<W> {
+    type Error = LimitError<W::Error>;
+
+    fn write(&mut self, byte: u8) -> Result<(), Self::Error> {
+        if byte != 0 && self.len == self.max {
+            return Err(LimitError::LimitExceeded);
+        }
+        self.w.write(byte).map_err(LimitError::Writer)?;
+        if byte == 0 {
+            self.len = 0;
+        } else {
+            self.len += 1;
+        }
+        Ok(())
+    }
+}
+
+impl<W> Encoder<LimitWriter<W>> {
+    /// Create a new encoder failing on messages longer than `max` encoded bytes.
+    ///
+    /// [write](Self::write) and [end](Self::end) fail with [LimitError::LimitExceeded]
+    /// as soon as the message can't fit in `max` bytes. A message of `n` bytes is
+    /// guaranteed to fit if `max` is at least [max_encoded_len]`(n)`.
+    pub const fn with_max_encoded(w: W, max: usize) -> Self {
+        Self::new(LimitWriter::new(w, max))
+    }
+}
+
+/// Writer writing into a byte slice.
+pub struct SliceWriter<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+}
+
+/// Error indicating the output buffer is too small.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferOverflowError;
+
+impl<'a> SliceWriter<'a> {
+    /// Create a new writer writing to the start of `buf`.
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    /// Create a new writer writing to `buf`, starting at `offset`.
+    ///
+    /// The first `offset` bytes are left untouched, but count as written.
+    ///
+    /// Panics if `offset` is greater than the length of `buf`.
+    pub fn with_offset(buf: &'a mut [u8], offset: usize) -> Self {
+        assert!(offset <= buf.len(), "offset out of bounds");
+        Self { buf, pos: offset }
+    }
+
+    /// Number of bytes written so far, counting from the start of the buffer.
+    pub fn written(&self) -> usize {
+        self.pos
+    }
+
+    /// Number of bytes that can still be written before the buffer is full.
+    ///
+    /// To check whether a message fits, compare with [max_encoded_len], plus one for
+    /// its separator.
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+
+    /// Consume the writer, returning the written part of the buffer.
+    pub fn into_written(self) -> &'a mut [u8] {
+        &mut self.buf[..self.pos]
+    }
+}
+
+impl<'a> Write for SliceWriter<'a> {
+    type Error = BufferOverflowError;
+
+    fn write(&mut self, byte: u8) -> Result<(), Self::Error> {
+        *self.buf.get_mut(self.pos).ok_or(BufferOverflowError)? = byte;
+        self.pos += 1;
+        Ok(())
+    }
+}
+
+impl<'a> TruncatableWrite for SliceWriter<'a> {
+    fn mark(&self) -> usize {
+        self.pos
+    }
+
+    fn truncate_to(&mut self, mark: usize) {
+        self.pos = self.pos.min(mark);
+    }
+}
+
+/// Version of the rzCOBS format implemented by this crate.
+///
+/// It only changes if the encoding changes, in which case [format_params] may change
+/// too. Tools matching this crate's format can check it in their tests.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Parameters of the rzCOBS format, as returned by [format_params].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatParams {
+    /// Bytes in a zero group, one per bit of its header.
+    pub group_size: usize,
+    /// Bytes in the longest run, for the `0xff` header.
+    pub max_run: usize,
+    /// Byte ending each message in a stream.
+    pub terminator: u8,
+}
+
+/// Parameters of the rzCOBS format implemented by this crate, see [FORMAT_VERSION].
+pub const fn format_params() -> FormatParams {
+    FormatParams {
+        group_size: 7,
+        max_run: 134,
+        terminator: 0x00,
+    }
+}
+
+/// Maximum encoded length for a message of `len` bytes.
+///
+/// The overhead is at most one byte per 134 message bytes, rounded up. This
+/// does not include the `0x00` separator byte. Saturates at `usize::MAX`.
+pub const fn max_encoded_len(len: usize) -> usize {
+    len.saturating_add(len.div_ceil(134))
+}
+
+/// Exact encoded length for a message.
+///
+/// This does not include the `0x00` separator byte. It is a `const fn`, so it can
+/// be used to size the output of [const_encode].
+pub const fn encoded_len(data: &[u8]) -> usize {
+    const_encode_helper::<0>(data).1
+}
+
+/// Encode a full message at compile time.
+///
+/// `N` must be the exact encoded length, as returned by [encoded_len]. This works
+/// for any message, but is slow, so it is meant for short constant messages:
+///
+/// ```
+/// const PING: [u8; rzcobs::encoded_len(b"ping")] = rzcobs::const_encode(b"ping");
+/// assert_eq!(&PING, b"pingp");
+/// ```
+///
+/// Panics if `N` is not the encoded length, which makes compilation fail when used
+/// to initialize a `const`.
+pub const fn const_encode<const N: usize>(data: &[u8]) -> [u8; N] {
+    let (res, len) = const_encode_helper::<N>(data);
+    assert!(len == N, "N is not the encoded length");
+    res
+}
+
+/// Same as [Encoder], in a `const fn`. Returns the first `N` encoded bytes and the
+/// full encoded length.
+const fn const_encode_helper<const N: usize>(data: &[u8]) -> ([u8; N], usize) {
+    let mut res = [0; N];
+    let mut len = 0;
+    macro_rules! push {
+        ($x:expr) => {{
+            if len < N {
+                res[len] = $x;
+            }
+            len += 1;
+        }};
+    }
+
+    let mut run = 0;
+    let mut zeros = 0;
+    let mut i = 0;
+    while i < data.len() {
+        let byte = data[i];
+        i += 1;
+        if run < 7 {
+            if byte == 0 {
+                zeros |= 1 << run;
+            } else {
+                push!(byte);
+            }
+            run += 1;
+            if run == 7 && zeros != 0x00 {
+                push!(zeros);
+                run = 0;
+                zeros = 0;
+            }
+        } else if byte == 0 {
+            push!((run - 7) | 0x80);
+            run = 0;
+            zeros = 0;
+        } else {
+            push!(byte);
+            run += 1;
+            if run == 134 {
+                push!(0xFF);
+                run = 0;
+                zeros = 0;
+            }
+        }
+    }
+    match run {
+        0 => {}
+        1..=6 => push!((zeros | (0xFF << run)) & 0x7F),
+        _ => push!((run - 7) | 0x80),
+    }
+    (res, len)
+}
+
+/// Encode a full message into a slice.
+///
+/// Returns the part of `res` containing the encoded message, which does not
+/// include any `0x00` separator byte. A buffer of [max_encoded_len] bytes is
+/// always big enough.
+pub fn encode_to_slice<'a>(
+    data: &[u8],
+    res: &'a mut [u8],
+) -> Result<&'a mut [u8], BufferOverflowError> {
+    encode_to_slice_at(data, res, 0)
+}
+
+/// Encode a full message into a slice, starting at `offset`.
+///
+/// The first `offset` bytes of `res` are left untouched, for example to fill in a
+/// header afterwards. Returns the part of `res` up to the end of the encoded message,
+/// including these first `offset` bytes.
+pub fn encode_to_slice_at<'a>(
+    data: &[u8],
+    res: &'a mut [u8],
+    offset: usize,
+) -> Result<&'a mut [u8], BufferOverflowError> {
+    if offset > res.len() {
+        return Err(BufferOverflowError);
+    }
+    let mut enc = Encoder::new(SliceWriter::with_offset(res, offset));
+    for &b in data {
+        enc.write(b)?;
+    }
+    enc.end()?;
+    Ok(enc.w.into_written())
+}
+
+/// Encode a message of compile-time-known length into a stack array.
+///
+/// Evaluates to a tuple `([u8; max_encoded_len(N)], usize)` with the buffer and
+/// the encoded length, so the encoded message is `&buf[..len]`. No `0x00`
+/// separator byte is included.
+///
+/// With a single argument, the length of `data` must be usable in a const context,
+/// for example a byte string literal or a `const` array. For runtime data, pass
+/// the length explicitly as `rzcobs_encode!(data; N)`, where `data` must be a
+/// `[u8; N]`.
+///
+/// `rzcobs_encode!(data; N)` expands to roughly:
+///
+/// ```ignore
+/// {
+///     let data: &[u8; N] = &data;
+///     let mut buf = [0u8; rzcobs::max_encoded_len(N)];
+///     let len = rzcobs::encode_to_slice(data, &mut buf).unwrap().len();
+///     (buf, len)
+/// }
+/// ```
+///
+/// # Example
+///
+/// ```
+/// let (buf, len) = rzcobs::rzcobs_encode!(b"ping");
+/// assert_eq!(&buf[..len], b"pingp");
+///
+/// let data = [1, 0, 2, 0];
+/// let (buf, len) = rzcobs::rzcobs_encode!(data; 4);
+/// assert_eq!(&buf[..len], &[1, 2, 0x7a]);
+/// ```
+#[macro_export]
+macro_rules! rzcobs_encode {
+    ($data:expr) => {
+        $crate::rzcobs_encode!($data; $data.len())
+    };
+    ($data:expr; $len:expr) => {{
+        let data: &[u8; $len] = &$data;
+        let mut buf = [0u8; $crate::max_encoded_len($len)];
+        let len = match $crate::encode_to_slice(data, &mut buf) {
+            Ok(res) => res.len(),
+            Err(_) => unreachable!(),
+        };
+        (buf, len)
+    }};
+}
+
+/// Encode a full message.
+///
+/// Encodes a single message and returns it as a `Vec`. The returned data does
+/// not include any `0x00` separator byte, you have to add it yourself.
+///
+/// This is a convenience function using [Encoder] internally. For streaming encoding, use [Encoder].
+///
+/// The shortest messages are single bytes, which are one zero group padded to 7
+/// bytes: a non-zero byte `x` encodes to `[x, 0x7e]`, and `0x00` to `[0x7f]`. Both
+/// decode to the byte followed by 6 zeros.
+#[cfg(feature = "alloc")]
+pub fn encode(data: &[u8]) -> Vec<u8> {
+    let mut res = Vec::new();
+    encode_into(data, &mut res);
+    res
+}
+
+/// Encode a full message, appending it to a `Vec`.
+///
+/// Same as [encode], but allows reusing the `Vec` allocation, and building
+/// streams of several messages.
+#[cfg(feature = "alloc")]
+pub fn encode_into(data: &[u8], res: &mut Vec<u8>) {
+    let mut enc = Encoder::new(res);
+    enc.reserve(data.len());
+    for &b in data {
+        enc.write(b).unwrap();
+    }
+    let len = enc.end_at().unwrap();
+    debug_assert_eq!(len, encoded_len(data), "encoded length bookkeeping is off");
+}
+
+/// Encode a message stored back to front.
+///
+/// `rev_iter` yields the message's bytes last to first. The output is the same as
+/// [encode] on the message in its normal order. Encoding runs forward, so the
+/// iterator must be double-ended: it is walked from its back.
+#[cfg(feature = "alloc")]
+pub fn encode_rev<I: DoubleEndedIterator<Item = u8>>(rev_iter: I) -> Vec<u8> {
+    let mut res = Vec::new();
+    let mut enc = Encoder::new(&mut res);
+    for b in rev_iter.rev() {
+        enc.write(b).unwrap();
+    }
+    enc.end().unwrap();
+    res
+}
+
+/// Encode a message known to contain no zero bytes.
+///
+/// Same output as [encode], but faster, since the message can be copied in whole
+/// runs of 134 bytes without checking for zeros.
+///
+/// Passing a message with zero bytes is a logic error: it produces an invalid
+/// encoding, or panics with debug assertions enabled.
+#[cfg(feature = "alloc")]
+pub fn encode_nonzero(data: &[u8]) -> Vec<u8> {
+    debug_assert!(!data.contains(&0), "message contains a zero byte");
+    let mut res = Vec::with_capacity(max_encoded_len(data.len()));
+    let mut runs = data.chunks_exact(134);
+    for run in &mut runs {
+        res.extend_from_slice(run);
+        res.push(0xff);
+    }
+    let rest = runs.remainder();
+    res.extend_from_slice(rest);
+    push_run_header(&mut res, rest.len());
+    res
+}
+
+/// Encode a message made of `count` times `byte`.
+///
+/// Same output as [encode] on the expanded message, but computed directly from
+/// the run lengths.
+#[cfg(feature = "alloc")]
+pub fn encode_repeated(byte: u8, count: usize) -> Vec<u8> {
+    if byte == 0 {
+        // Groups of 7 zeros, the last one padded.
+        return alloc::vec![0x7f; count.div_ceil(7)];
+    }
+
+    let mut res = Vec::with_capacity(max_encoded_len(count));
+    for _ in 0..count / 134 {
+        res.resize(res.len() + 134, byte);
+        res.push(0xff);
+    }
+    let rest = count % 134;
+    res.resize(res.len() + rest, byte);
+    push_run_header(&mut res, rest);
+    res
+}
+
+/// Push the header for the last `n` non-zero bytes of a message, with `n < 134`.
+#[cfg(feature = "alloc")]
+fn push_run_header(res: &mut Vec<u8>, n: usize) {
+    match n {
+        0 => {}
+        // A short group, padded with zeros.
+        1..=6 => res.push((0xff << n) & 0x7f),
+        _ => res.push((n - 7) as u8 | 0x80),
+    }
+}
+
+/// Encode a message, followed by a `0x00` separator byte.
+///
+/// Same as [encode], but returns the message ready to be sent.
+#[cfg(feature = "alloc")]
+pub fn encode_framed(data: &[u8]) -> Vec<u8> {
+    let mut res = Vec::with_capacity(max_encoded_len(data.len()) + 1);
+    encode_into(data, &mut res);
+    res.push(0);
+    res
+}
+
+/// Reusable buffer for encoding many messages without allocating for each.
+///
+/// [encode](Self::encode) returns a slice borrowing the pool, so it must be
+/// consumed, for example copied into a socket, before the next message is encoded.
+/// The buffer grows to the longest encoded message, and keeps its capacity.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Default)]
+pub struct FramePool {
+    buf: Vec<u8>,
+}
+
+#[cfg(feature = "alloc")]
+impl FramePool {
+    /// Create a pool with an empty buffer.
+    pub const fn new() -> Self {
+        Self { buf: Vec::new() }
+    }
+
+    /// Create a pool with room for messages of up to `len` bytes.
+    pub fn with_capacity(len: usize) -> Self {
+        Self {
+            buf: Vec::with_capacity(max_encoded_len(len).saturating_add(1)),
+        }
+    }
+
+    /// Encode a message, followed by a `0x00` separator byte.
+    ///
+    /// Same as [encode_framed], but the returned slice is in the pool's buffer,
+    /// overwritten by the next message.
+    pub fn encode(&mut self, data: &[u8]) -> &[u8] {
+        self.buf.clear();
+        encode_into(data, &mut self.buf);
+        self.buf.push(0);
+        &self.buf
+    }
+
+    /// Capacity of the buffer, in bytes.
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+}
+
+/// Encode a message with its separator, as lowercase hex.
+///
+/// This is the format of this crate's test vectors, so logged messages can be pasted
+/// as is into a `hex!` literal.
+#[cfg(feature = "alloc")]
+pub fn encode_hex(data: &[u8]) -> String {
+    use core::fmt::Write as _;
+
+    let mut res = String::new();
+    for b in encode_framed(data) {
+        write!(res, "{:02x}", b).unwrap();
+    }
+    res
+}
+
+/// Encode a message with all bits inverted, followed by a `0xff` separator byte.
+///
+/// For transports idling at `0x00`, where `0x00` can't be the separator. The
+/// encoding never contains `0x00`, so once inverted it never contains `0xff`,
+/// which can then be the separator. Decode with [decode_framed_inverted].
+#[cfg(feature = "alloc")]
+pub fn encode_framed_inverted(data: &[u8]) -> Vec<u8> {
+    let mut res = Vec::with_capacity(max_encoded_len(data.len()) + 1);
+    let mut enc = Encoder::new(MapWriter::new(&mut res, |b: u8| !b));
+    enc.reserve(data.len());
+    enc.write_bytes(data).unwrap();
+    enc.end().unwrap();
+    res.push(0xff);
+    res
+}
+
+/// Where the bytes of an encoded message go, as returned by [encode_accounted].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EncodeStats {
+    /// Header bytes, one per group.
+    pub header_bytes: usize,
+    /// Literal bytes, copied from the message.
+    pub literal_bytes: usize,
+    /// Groups with a zero group header, see [HeaderKind::ZeroGroup].
+    pub zero_groups: usize,
+}
+
+/// Encode a full message, also counting its header and literal bytes.
+///
+/// `header_bytes + literal_bytes` is the encoded length. This is meant for
+/// analyzing the overhead of the encoding for different kinds of messages.
+#[cfg(feature = "alloc")]
+pub fn encode_accounted(data: &[u8]) -> (Vec<u8>, EncodeStats) {
+    let res = encode(data);
+    let mut stats = EncodeStats::default();
+    let mut rest = &res[..];
+    while !rest.is_empty() {
+        let (r, literals, header) = split_group(rest).unwrap();
+        stats.header_bytes += 1;
+        stats.literal_bytes += literals.len();
+        if header < 0x80 {
+            stats.zero_groups += 1;
+        }
+        rest = r;
+    }
+    (res, stats)
+}
+
+/// Encode several messages into a single stream.
+///
+/// Returns the stream, with the encoded messages separated by a `0x00` byte, and
+/// the offset in the stream where each encoded message starts.
+#[cfg(feature = "alloc")]
+pub fn encode_batch(messages: &[&[u8]]) -> (Vec<u8>, Vec<usize>) {
+    let mut res = Vec::new();
+    let mut offsets = Vec::with_capacity(messages.len());
+    for (i, msg) in messages.iter().enumerate() {
+        if i != 0 {
+            res.push(0);
+        }
+        offsets.push(res.len());
+        encode_into(msg, &mut res);
+    }
+    (res, offsets)
+}
+
+/// Encode a message, preceded by a hint of its length, not part of rzCOBS.
+///
+/// The hint is a single byte, before the encoded message: one more than the
+/// number of bits of the message length, so `1` for an empty message and `9` for
+/// one of 128 to 255 bytes. A receiver can preallocate [hint_capacity] bytes
+/// when it sees the hint, before the rest of the message arrives. The hint is
+/// never `0x00`, so it doesn't look like a separator. It's only an optimization,
+/// and it must be stripped before using [decode], or use [decode_with_hint].
+#[cfg(feature = "alloc")]
+pub fn encode_with_hint(data: &[u8]) -> Vec<u8> {
+    let mut res = Vec::with_capacity(max_encoded_len(data.len()) + 1);
+    res.push((usize::BITS - data.len().leading_zeros()) as u8 + 1);
+    encode_into(data, &mut res);
+    res
+}
+
+/// Maximum message length for a length hint from [encode_with_hint].
+///
+/// Returns 0 for the invalid hint `0`. The hint is not checked against the
+/// message, so cap the allocation if the sender is not trusted.
+pub const fn hint_capacity(hint: u8) -> usize {
+    match hint {
+        0 => 0,
+        _ if hint as u32 > usize::BITS => usize::MAX,
+        _ => (1 << (hint - 1)) - 1,
+    }
+}
+
+/// Decode a full message encoded with [encode_with_hint].
+///
+/// The hint byte is stripped and otherwise ignored, fails with
+/// [DecodeError::MalformedError] if it's missing.
+#[cfg(feature = "alloc")]
+pub fn decode_with_hint(data: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    match data.split_first() {
+        Some((_, data)) => decode_vec(data),
+        None => Err(DecodeError::MalformedError),
+    }
+}
+
+/// Encode a message read from `r` until EOF, writing it and a `0x00` separator byte to `w`.
+///
+/// The message is encoded as it is read, so only a small buffer is used no matter
+/// how long the message is. The output is written one byte at a time, so wrap
+/// unbuffered writers like `std::io::Stdout` in a [`std::io::BufWriter`].
+#[cfg(feature = "std")]
+pub fn encode_from_reader<R: std::io::Read, W: std::io::Write>(
+    mut r: R,
+    w: W,
+) -> std::io::Result<()> {
+    let mut enc = Encoder::new(IoWrite(w));
+    let mut buf = [0; 64];
+    loop {
+        let n = match r.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        enc.write_bytes(&buf[..n])?;
+    }
+    enc.end()?;
+    enc.writer().write(0)
+}
+
+/// Error indicating the decoded data was malformed reverse-COBS.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MalformedError;
+
+/// Decode a full message.
+///
+/// `data` must be a full rzCOBS encoded message. Decoding partial
+/// messages is not possible. `data` must NOT include any `0x00` separator byte.
+///
+/// With the `strict` feature, non-canonical messages are rejected too, see
+/// [decode_to_slice].
+#[cfg(feature = "alloc")]
+pub fn decode(data: &[u8]) -> Result<Vec<u8>, MalformedError> {
+    decode_vec(data).map_err(|_| MalformedError)
+}
+
+/// Decode a full message, without allocating for empty messages.
+///
+/// Same as [decode], but an empty `data` (the encoding of an empty message) returns
+/// a borrowed empty slice instead of allocating a `Vec`.
+///
+/// Note that only empty frames are special-cased. Small frames that decode to zeros,
+/// like `7f`, still allocate.
+#[cfg(feature = "alloc")]
+pub fn decode_cow(data: &[u8]) -> Result<Cow<'static, [u8]>, DecodeError> {
+    if data.is_empty() {
+        return Ok(Cow::Borrowed(&[]));
+    }
+    decode_vec(data).map(Cow::Owned)
+}
+
+/// Decode a full message, also returning how many trailing bytes may be padding.
+///
+/// The padding is made of the trailing zeros output by the last group. It can't
+/// be told apart from zeros that were really at the end of the message, so the
+/// returned count is an upper bound: the original message is between
+/// `res.len() - padding` and `res.len()` bytes long. Protocols whose messages never
+/// end with a zero can trim all of it.
+///
+/// A last group with fewer than 7 bytes gets padded up to 7, and a run of non-zero
+/// bytes ends with one zero, so the padding is 1 to 6 bytes for a message ending with
+/// a non-zero byte.
+#[cfg(feature = "alloc")]
+pub fn decode_ex(data: &[u8]) -> Result<(Vec<u8>, usize), DecodeError> {
+    Ok((decode_vec(data)?, padding_len(data)))
+}
+
+/// Byte of a message decoded with [decode_debug].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodedByte {
+    /// A byte of the message.
+    Data(u8),
+    /// A zero byte that may be padding, see [decode_ex].
+    Padding,
+}
+
+/// Decode a full message, marking the trailing zeros that may be padding.
+///
+/// This is meant for debugging and inspection tools, to show why decoded messages
+/// can be longer than the original ones. Use [decode_ex] to get the padding length
+/// instead.
+#[cfg(feature = "alloc")]
+pub fn decode_debug(data: &[u8]) -> Result<Vec<DecodedByte>, DecodeError> {
+    let res = decode_vec(data)?;
+    let len = res.len() - padding_len(data);
+    Ok(res
+        .iter()
+        .enumerate()
+        .map(|(i, &b)| {
+            if i < len {
+                DecodedByte::Data(b)
+            } else {
+                DecodedByte::Padding
+            }
+        })
+        .collect())
+}
+
+/// Decode a full message into a `Vec` using the allocator `alloc`.
+///
+/// Same as [decode], for example to decode many messages into an arena. Only
+/// available with the `allocator_api` feature, which needs a nightly compiler.
+#[cfg(feature = "allocator_api")]
+pub fn decode_in<A: core::alloc::Allocator>(
+    data: &[u8],
+    alloc: A,
+) -> Result<Vec<u8, A>, DecodeError> {
+    let len = decoded_len(data)?;
+    let mut res = Vec::with_capacity_in(len, alloc);
+    res.resize(len, 0);
+    decode_to_slice_with(data, &mut res, DecodeStrategy::BackFill)?;
+    Ok(res)
+}
+
+/// Decode a full message that may be stored back to front.
+///
+/// Tries decoding `data`, then reversed if that fails, for captures from tools
+/// that store messages in either byte order. Fails with the error for `data` in
+/// its given order if neither works.
+///
+/// This is a heuristic: a reversed message may decode fine, to garbage, though
+/// it's unlikely for messages longer than a few groups. Data that decodes in the
+/// given order is never tried reversed.
+#[cfg(feature = "alloc")]
+pub fn try_decode_both(data: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    decode_vec(data).or_else(|e| {
+        let reversed: Vec<u8> = data.iter().rev().copied().collect();
+        decode_vec(&reversed).map_err(|_| e)
+    })
+}
+
+/// Decode a full message of at most `cap` bytes, not counting padding.
+///
+/// Trailing bytes that may be padding, see [decode_ex], are dropped past `cap`.
+/// Unlike decoding with [decode_to_slice] into a `cap` byte buffer, which fails if
+/// the padding doesn't fit too, this only fails if the message is longer than `cap`
+/// even without its padding, with [DecodeError::BufferOverflow]. Nothing is
+/// allocated then.
+#[cfg(feature = "alloc")]
+pub fn decode_capped(data: &[u8], cap: usize) -> Result<Vec<u8>, DecodeError> {
+    let len = decoded_len(data)?;
+    if len - padding_len(data) > cap {
+        return Err(DecodeError::BufferOverflow { needed: len });
+    }
+    let mut res = decode_vec(data)?;
+    res.truncate(cap);
+    Ok(res)
+}
+
+/// Framing variants supported by [decode_variant].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CobsVariant {
+    /// Reverse-Zerocompressing-COBS, as produced by [Encoder].
+    Rzcobs,
+    /// [rCOBS](https://github.com/Dirbaio/rcobs), reverse COBS without zero compression.
+    Rcobs,
+}
+
+/// Decode a full message in the given framing variant.
+///
+/// Handy when receiving from devices using different framings, to get the same
+/// output and error types for all of them. `data` must NOT include any `0x00`
+/// separator byte.
+///
+/// Unlike rzCOBS, rCOBS has no padding, so [CobsVariant::Rcobs] returns the
+/// exact original message.
+#[cfg(feature = "alloc")]
+pub fn decode_variant(data: &[u8], variant: CobsVariant) -> Result<Vec<u8>, DecodeError> {
+    match variant {
+        CobsVariant::Rzcobs => decode_vec(data),
+        CobsVariant::Rcobs => {
+            let mut res = Vec::new();
+            decode_rcobs_helper(data, &mut res)?;
+            Ok(res)
+        }
+    }
+}
+
+/// Decode a message encoded with [encode_framed_inverted].
+///
+/// `data` may end with the `0xff` separator byte, which is ignored.
+#[cfg(feature = "alloc")]
+pub fn decode_framed_inverted(data: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let data = data.strip_suffix(&[0xff]).unwrap_or(data);
+    let inverted: Vec<u8> = data.iter().map(|&b| !b).collect();
+    decode_vec(&inverted)
+}
+
+/// Decode the first message in `data`, up to its `0x00` separator byte.
+///
+/// Returns the decoded message and the number of bytes of `data` used, including
+/// the separator. What follows the separator is ignored. If `data` has no separator,
+/// all of it is decoded as one message.
+#[cfg(feature = "alloc")]
+pub fn decode_until_separator(data: &[u8]) -> Result<(Vec<u8>, usize), DecodeError> {
+    let (frame, consumed) = match data.iter().position(|&b| b == 0) {
+        Some(pos) => (&data[..pos], pos + 1),
+        None => (data, data.len()),
+    };
+    Ok((decode_vec(frame)?, consumed))
+}
+
+/// Decode the messages in a stream joined mid-message.
+///
+/// When joining a stream at an arbitrary point, the bytes before the first `0x00`
+/// are most likely the tail of a message whose beginning was missed. This discards
+/// everything up to and including the first `0x00`, then decodes each following
+/// `0x00`-terminated message.
+///
+/// Returns the results for each message and the number of bytes of `stream` that
+/// were consumed. Trailing bytes not terminated by a `0x00` are not consumed, they
+/// are the beginning of a message that is not fully received yet. If `stream`
+/// contains no `0x00` at all, all of it is discarded and consumed.
+///
+/// Only call this once, when joining the stream. Once in sync, decode the rest of
+/// the stream normally, since calling this again would discard a valid message.
+#[cfg(feature = "alloc")]
+pub fn decode_resync(stream: &[u8]) -> (Vec<Result<Vec<u8>, DecodeError>>, usize) {
+    let mut res = Vec::new();
+    let mut consumed = match stream.iter().position(|&b| b == 0) {
+        Some(pos) => pos + 1,
+        None => return (res, stream.len()),
+    };
+
+    while let Some(len) = stream[consumed..].iter().position(|&b| b == 0) {
+        res.push(decode_vec(&stream[consumed..][..len]));
+        consumed += len + 1;
+    }
+    (res, consumed)
+}
+
+/// Decode a message, modify it with `f`, and encode it again, appending it to `out`.
+///
+/// Handy for proxies altering messages on their way. The padding of the decoded
+/// message is passed to `f` too. It encodes back to the same bytes, so it doesn't
+/// grow when a message goes through several proxies. Nothing is appended to `out`
+/// if `frame` is malformed.
+#[cfg(feature = "alloc")]
+pub fn map_frame<F: FnOnce(&mut [u8])>(
+    frame: &[u8],
+    out: &mut Vec<u8>,
+    f: F,
+) -> Result<(), DecodeError> {
+    let mut msg = decode_vec(frame)?;
+    f(&mut msg);
+    encode_into(&msg, out);
+    Ok(())
+}
+
+/// How [split_frames] handles empty messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SplitMode {
+    /// Treat consecutive `0x00` separators as one, returning no empty messages.
+    ///
+    /// This is the default, since many transports send extra `0x00` bytes when
+    /// idle, or to make sure the receiver resyncs.
+    #[default]
+    SkipEmpty,
+    /// Return an empty message before each `0x00` not terminating a message,
+    /// for protocols where empty messages mean something.
+    YieldEmpty,
+}
+
+/// Decode a full message, limiting its decoded length relative to the encoded length.
+///
+/// Fails with [DecodeError::RatioExceeded] if the message decodes to more than
+/// `data.len() * max_ratio` bytes. The headers are checked before allocating, so a
+/// malicious message can't make this allocate more than the limit.
+///
+/// Each encoded byte decodes to at most 7 bytes, see [max_decoded_len], so a
+/// `max_ratio` of 7 or more never fails.
+#[cfg(feature = "alloc")]
+pub fn decode_ratio_limited(data: &[u8], max_ratio: usize) -> Result<Vec<u8>, DecodeError> {
+    let max = data.len().saturating_mul(max_ratio);
+    let mut len = 0;
+    let mut rest = data;
+    while !rest.is_empty() {
+        let (r, _, header) = split_group(rest)?;
+        len = header_len(header).unwrap().1.saturating_add(len);
+        if len > max {
+            return Err(DecodeError::RatioExceeded);
+        }
+        rest = r;
+    }
+    decode_vec(data)
+}
+
+/// Split a stream into its encoded messages.
+///
+/// Messages are terminated by `0x00` bytes, which are not included. The last
+/// message doesn't need a terminating separator. Empty messages, that is `0x00`
+/// bytes right after another one or at the start of `data`, are handled
+/// according to `mode`.
+///
+/// This collects [Frames], use it directly to avoid allocating.
+#[cfg(feature = "alloc")]
+pub fn split_frames(data: &[u8], mode: SplitMode) -> Vec<&[u8]> {
+    Frames::new(data, mode).collect()
+}
+
+/// Result of feeding a byte to [FrameAccumulator::push_status].
+///
+/// Unlike [decode], which assumes it's given a complete message, a streaming
+/// decoder tells a message not received in full yet apart from a malformed one.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrameStatus {
+    /// The byte was a separator, ending this message.
+    Complete(Vec<u8>),
+    /// No message ended, keep feeding bytes.
+    Incomplete,
+    /// The message ended by this byte is malformed, or is too long.
+    Err(DecodeError),
+}
+
+/// Decoder collecting a stream one byte at a time into messages.
+///
+/// Bytes are buffered until a `0x00` separator, then the message is decoded. The
+/// maximum encoded message length is mandatory, so that a peer never sending a
+/// separator can't make the buffer grow without limit. Empty messages are skipped.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone)]
+pub struct FrameAccumulator {
+    buf: Vec<u8>,
+    max_frame_bytes: usize,
+    /// A message too long was reported, and its rest must be discarded.
+    discarding: bool,
+}
+
+#[cfg(feature = "alloc")]
+impl FrameAccumulator {
+    /// Create a decoder for messages of up to `max_frame_bytes` encoded bytes, not
+    /// counting the separator.
+    pub const fn new(max_frame_bytes: usize) -> Self {
+        Self {
+            buf: Vec::new(),
+            max_frame_bytes,
+            discarding: false,
+        }
+    }
+
+    /// Process a received byte.
+    ///
+    /// Returns the decoded message when `byte` is the separator ending it. If the
+    /// message gets longer than the maximum, returns [DecodeError::FrameTooLong]
+    /// right away, once, and discards the rest of it up to its separator.
+    pub fn push(&mut self, byte: u8) -> Option<Result<Vec<u8>, DecodeError>> {
+        match self.push_status(byte) {
+            FrameStatus::Complete(msg) => Some(Ok(msg)),
+            FrameStatus::Incomplete => None,
+            FrameStatus::Err(e) => Some(Err(e)),
+        }
+    }
+
+    /// Process a received byte, like [push](Self::push), returning a [FrameStatus].
+    pub fn push_status(&mut self, byte: u8) -> FrameStatus {
+        if byte == 0 {
+            if self.discarding {
+                self.discarding = false;
+                return FrameStatus::Incomplete;
+            }
+            if self.buf.is_empty() {
+                return FrameStatus::Incomplete;
+            }
+            let res = decode_vec(&self.buf);
+            self.buf.clear();
+            return match res {
+                Ok(msg) => FrameStatus::Complete(msg),
+                Err(e) => FrameStatus::Err(e),
+            };
+        }
+
+        if self.discarding {
+            return FrameStatus::Incomplete;
+        }
+        if self.buf.len() == self.max_frame_bytes {
+            self.buf.clear();
+            self.discarding = true;
+            return FrameStatus::Err(DecodeError::FrameTooLong);
+        }
+        self.buf.push(byte);
+        FrameStatus::Incomplete
+    }
+
+    /// Whether part of a message was received, and its separator is still missing.
+    ///
+    /// Bytes of a message too long, being discarded, don't count.
+    pub fn is_incomplete(&self) -> bool {
+        !self.buf.is_empty()
+    }
+
+    /// Drop the partially received message, if any, for example after the link was
+    /// reset. The next byte starts a new message. The buffer keeps its capacity.
+    pub fn reset(&mut self) {
+        self.buf.clear();
+        self.discarding = false;
+    }
+}
+
+/// Decoder collecting a stream one byte at a time into messages, without allocating.
+///
+/// Same as [FrameAccumulator], but messages of up to `CAP` encoded bytes are
+/// buffered in an array, and decoded with [decode_to_slice] into an output buffer
+/// given by the caller. It never panics nor allocates, and decoding takes time
+/// linear in `CAP`, so [push](Self::push) can be called from an interrupt handler.
+pub struct RingDecoder<'a, const CAP: usize> {
+    out: &'a mut [u8],
+    buf: [u8; CAP],
+    len: usize,
+    discarding: bool,
+}
+
+impl<'a, const CAP: usize> RingDecoder<'a, CAP> {
+    /// Create a decoder decoding messages into `out`.
+    ///
+    /// Messages decoding to more than `out.len()` bytes fail with
+    /// [DecodeError::BufferOverflow]. Use [max_decoded_len] of `CAP` for `out.len()`
+    /// so that it never happens.
+    pub fn new(out: &'a mut [u8]) -> Self {
+        Self {
+            out,
+            buf: [0; CAP],
+            len: 0,
+            discarding: false,
+        }
+    }
+
+    /// Drop the partially received message, if any. The next byte starts a new message.
+    pub fn reset(&mut self) {
+        self.len = 0;
+        self.discarding = false;
+    }
+
+    /// Process a received byte.
+    ///
+    /// When `byte` is the separator ending a message, decodes it and returns the
+    /// result, borrowing the output buffer until the next call. If the message gets
+    /// longer than `CAP`, returns [DecodeError::FrameTooLong] right away, once, and
+    /// discards the rest of it up to its separator.
+    pub fn push(&mut self, byte: u8) -> Option<Result<&[u8], DecodeError>> {
+        if byte == 0 {
+            let len = core::mem::replace(&mut self.len, 0);
+            if core::mem::replace(&mut self.discarding, false) || len == 0 {
+                return None;
+            }
+            return Some(decode_to_slice(&self.buf[..len], self.out).map(|res| &*res));
+        }
+
+        if self.discarding {
+            return None;
+        }
+        if self.len == CAP {
+            self.len = 0;
+            self.discarding = true;
+            return Some(Err(DecodeError::FrameTooLong));
+        }
+        self.buf[self.len] = byte;
+        self.len += 1;
+        None
+    }
+}
+
+/// Convert a `0x00`-separated stream into length-prefixed encoded messages.
+///
+/// The stream is split with [frames], and each message is written as its encoded
+/// length, as an unsigned LEB128 varint, followed by the encoded message. The
+/// messages are not decoded, only their headers are checked, so that malformed
+/// messages are not forwarded. Fails on the first malformed message.
+#[cfg(feature = "alloc")]
+pub fn reframe_to_len_prefixed(stream: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let mut res = Vec::with_capacity(stream.len());
+    for frame in frames(stream) {
+        decoded_len(frame)?;
+        push_varint(&mut res, frame.len());
+        res.extend_from_slice(frame);
+    }
+    Ok(res)
+}
+
+/// Append `x` as an unsigned LEB128 varint.
+#[cfg(feature = "alloc")]
+fn push_varint(res: &mut Vec<u8>, x: usize) {
+    res.extend(varint(x));
+}
+
+/// Bytes of `x` as an unsigned LEB128 varint.
+fn varint(mut x: usize) -> impl Iterator<Item = u8> {
+    let mut done = false;
+    core::iter::from_fn(move || {
+        if done {
+            None
+        } else if x >= 0x80 {
+            let b = x as u8 | 0x80;
+            x >>= 7;
+            Some(b)
+        } else {
+            done = true;
+            Some(x as u8)
+        }
+    })
+}
+
+/// Parse an unsigned LEB128 varint at the start of `data`.
+///
+/// Returns the value and its length, or `None` if it's truncated or overflows.
+fn read_varint(data: &[u8]) -> Option<(usize, usize)> {
+    let mut x = 0usize;
+    for (i, &b) in data.iter().enumerate() {
+        let shift = 7 * i as u32;
+        let bits = (b & 0x7f) as usize;
+        if shift >= usize::BITS || (bits << shift) >> shift != bits {
+            return None;
+        }
+        x |= bits << shift;
+        if b & 0x80 == 0 {
+            return Some((x, i + 1));
+        }
+    }
+    None
+}
+
+/// Compute the encoded and decoded length of each message in a stream.
+///
+/// The stream is split with [frames], and for each message returns its encoded
+/// length and its decoded length without the trailing zeros that may be padding,
+/// which is the shortest the original message can be. Only headers are looked at,
+/// nothing is decoded. Malformed messages get the error instead of the decoded
+/// length.
+#[cfg(feature = "alloc")]
+pub fn frame_sizes(data: &[u8]) -> Vec<(usize, Result<usize, DecodeError>)> {
+    frames(data)
+        .map(|frame| {
+            let len = decoded_len(frame).map(|len| len - padding_len(frame));
+            (frame.len(), len)
+        })
+        .collect()
+}
+
+/// Iterate over the encoded messages in a stream, skipping empty messages.
+///
+/// Same as [split_frames] with [SplitMode::SkipEmpty], but lazy. The messages
+/// borrow from `data`, so they can be decoded one by one with [decode_to_slice]
+/// without allocating.
+pub fn frames(data: &[u8]) -> Frames<'_> {
+    Frames::new(data, SplitMode::SkipEmpty)
+}
+
+/// Check that all the messages in a stream are valid, without decoding them.
+///
+/// The stream is split with [frames], and each message is checked with
+/// [decoded_len], so nothing is allocated. Returns the number of messages, or the
+/// index of the first malformed one with its error.
+pub fn verify_all(stream: &[u8]) -> Result<usize, (usize, DecodeError)> {
+    let mut count = 0;
+    for frame in frames(stream) {
+        decoded_len(frame).map_err(|e| (count, e))?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Iterator over the encoded messages in a stream, see [frames].
+#[derive(Debug, Clone)]
+pub struct Frames<'a> {
+    data: &'a [u8],
+    mode: SplitMode,
+}
+
+impl<'a> Frames<'a> {
+    /// Create an iterator over the messages in `data`, handling empty messages
+    /// according to `mode`.
+    pub fn new(data: &'a [u8], mode: SplitMode) -> Self {
+        Self { data, mode }
+    }
+}
+
+impl<'a> Iterator for Frames<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        while !self.data.is_empty() {
+            let (frame, rest) = match self.data.iter().position(|&b| b == 0) {
+                Some(pos) => (&self.data[..pos], &self.data[pos + 1..]),
+                None => (self.data, &[][..]),
+            };
+            self.data = rest;
+            if !frame.is_empty() || self.mode == SplitMode::YieldEmpty {
+                return Some(frame);
+            }
+        }
+        None
+    }
+}
+
+/// Decode all the messages in a capture of a stream.
+///
+/// The capture is split with [split_frames] skipping empty messages, and each message is decoded on its
+/// own, so a malformed message doesn't prevent decoding the others. Failed messages
+/// come with the offset in `data` where they start.
+#[cfg(feature = "alloc")]
+pub fn decode_capture(data: &[u8]) -> Vec<Result<Vec<u8>, (usize, DecodeError)>> {
+    split_frames(data, SplitMode::SkipEmpty)
+        .into_iter()
+        .map(|frame| {
+            let offset = frame.as_ptr() as usize - data.as_ptr() as usize;
+            decode_vec(frame).map_err(|e| (offset, e))
+        })
+        .collect()
+}
+
+/// Decode the frames of a defmt log stream, with their index.
+///
+/// defmt's rzCOBS encoding ends each log frame with a `0x00` separator, and may send
+/// a lone `0x00` when idle or after the target resets, to get the host back in sync.
+/// These show up as empty frames, which are skipped, and don't count for the index:
+/// it is the position of the frame among the non-empty ones. Each frame is decoded
+/// on its own, so a malformed frame doesn't prevent decoding the others.
+#[cfg(feature = "alloc")]
+pub fn defmt_frames_indexed(
+    stream: &[u8],
+) -> impl Iterator<Item = (usize, Result<Vec<u8>, DecodeError>)> + '_ {
+    frames(stream).map(decode_vec).enumerate()
+}
+
+/// Error decoding a message.
+///
+/// More variants may be added in minor releases, so matches on it need a wildcard arm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DecodeError {
+    MalformedError,
+    /// The output buffer is too small. `needed` is the length of the full decoded message.
+    BufferOverflow {
+        needed: usize,
+    },
+    /// The decoded message is shorter than expected.
+    LengthMismatch,
+    /// The message is truncated: a header needs `needed` stream bytes before it,
+    /// but only `found` are left.
+    ///
+    /// When receiving a message in parts, this means it is most likely incomplete
+    /// rather than corrupt.
+    Truncated {
+        needed: usize,
+        found: usize,
+    },
+    /// The message is not in the form the encoder produces: it has a `0x00` literal
+    /// byte. Only returned with the `strict` feature.
+    NonCanonical,
+    /// The decoded message would be too large compared to the encoded one, see
+    /// [decode_ratio_limited].
+    RatioExceeded,
+    /// The message is longer than the maximum length set for a decoder accumulating
+    /// messages, such as [FrameAccumulator]. The rest of it is discarded.
+    FrameTooLong,
+    /// The range of the message is out of bounds of the data holding it, see
+    /// [decode_range].
+    RangeOutOfBounds,
+    /// A packet of a message split by [ChunkedFrameWriter] is missing or out of
+    /// order. The rest of the message is discarded.
+    PacketLost,
+}
+
+/// Collections that allow pushing u8 and reversing the element order in place
+trait Buffer {
+    fn try_push(&mut self, x: u8) -> Result<(), DecodeError>;
+    fn rev(&mut self);
+}
+
+#[cfg(feature = "alloc")]
+impl Buffer for Vec<u8> {
+    fn try_push(&mut self, x: u8) -> Result<(), DecodeError> {
+        Vec::<u8>::push(self, x);
+
+        Ok(())
+    }
+
+    fn rev(&mut self) {
+        self.reverse();
+    }
+}
+
+/// Decode a full message.
+///
+/// `data` must be a full rzCOBS encoded message. Decoding partial
+/// messages is not possible. `data` must NOT include any `0x00` separator byte.
+///
+/// Fails with [DecodeError::BufferOverflow] if `res` is too short, giving the length
+/// needed. Every non-empty message decodes to at least 7 bytes, so with an empty
+/// `res` this is the error for any valid non-empty message. The empty message
+/// decodes to an empty slice, even with an empty `res`.
+///
+/// # Strict mode
+///
+/// The only non-canonical messages, that decode fine but are never produced by the
+/// encoder, are those with a `0x00` literal byte. They can't come from a compliant
+/// encoder, since a `0x00` in the stream would be taken as a separator. With the
+/// `strict` feature, these return [DecodeError::NonCanonical], for all decoding
+/// functions.
+///
+/// This costs an extra scan over the literal bytes, and rejects messages that
+/// non-strict builds accept. Since cargo features are unified, enabling it also
+/// affects other crates in the build using rzcobs.
+pub fn decode_to_slice<'a>(data: &[u8], res: &'a mut [u8]) -> Result<&'a mut [u8], DecodeError> {
+    let mut res = SliceBuffer {
+        data: res,
+        len: 0,
+        skip: 0,
+    };
+    decode_helper(data, &mut res).map_err(|e| match e {
+        DecodeError::BufferOverflow { .. } => match decoded_len(data) {
+            Ok(needed) => DecodeError::BufferOverflow { needed },
+            Err(e) => e,
+        },
+        e => e,
+    })?;
+
+    debug_assert_eq!(
+        Ok(res.len),
+        decoded_len(data),
+        "decoded length bookkeeping is off"
+    );
+    Ok(&mut res.data[..res.len])
+}
+
+/// Decode a full message stored in `blob` at `range`.
+///
+/// Same as [decode_to_slice] with `&blob[range]`, except that an invalid range, out of
+/// bounds or with its start past its end, fails with [DecodeError::RangeOutOfBounds]
+/// instead of panicking.
+pub fn decode_range<'a>(
+    blob: &[u8],
+    range: core::ops::Range<usize>,
+    res: &'a mut [u8],
+) -> Result<&'a mut [u8], DecodeError> {
+    let data = blob.get(range).ok_or(DecodeError::RangeOutOfBounds)?;
+    decode_to_slice(data, res)
+}
+
+/// How [decode_to_slice_with] fills the output buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DecodeStrategy {
+    /// Write the decoded bytes from the start of the buffer as they come, which is
+    /// in reverse order, then reverse them. This is what [decode_to_slice] does.
+    #[default]
+    FrontFill,
+    /// Compute the decoded length with [decoded_len] first, then write the decoded
+    /// bytes from the end of the message, so they don't need to be reversed. This
+    /// scans the headers twice, but saves the reversal. Which one is faster depends
+    /// on the message, so measure before choosing.
+    BackFill,
+}
+
+/// Decode a full message, choosing how the output buffer is filled.
+///
+/// Same as [decode_to_slice], which uses [DecodeStrategy::FrontFill]. Both
+/// strategies give the same results.
+pub fn decode_to_slice_with<'a>(
+    data: &[u8],
+    res: &'a mut [u8],
+    strategy: DecodeStrategy,
+) -> Result<&'a mut [u8], DecodeError> {
+    match strategy {
+        DecodeStrategy::FrontFill => decode_to_slice(data, res),
+        DecodeStrategy::BackFill => {
+            let (len, _) = decode_to_split_slices(data, res, &mut [])?;
+            Ok(&mut res[..len])
+        }
+    }
+}
+
+/// Decode a full message, also returning how many trailing bytes may be padding.
+///
+/// Same as [decode_ex], without allocating: the decoded message, padding included,
+/// is at the start of `res`. Like there, the padding count is an upper bound, since
+/// padding can't be told apart from trailing zeros of the message.
+pub fn decode_to_slice_trimmed<'a>(
+    data: &[u8],
+    res: &'a mut [u8],
+) -> Result<(&'a mut [u8], usize), DecodeError> {
+    let res = decode_to_slice(data, res)?;
+    Ok((res, padding_len(data)))
+}
+
+/// Slice filled by the decoder.
+struct SliceBuffer<'a> {
+    data: &'a mut [u8],
+    len: usize,
+    /// Number of bytes to drop before writing to `data`. Since the decoder outputs
+    /// bytes in reverse order, these are the last bytes of the message.
+    skip: usize,
+}
+
+impl<'a> Buffer for SliceBuffer<'a> {
+    fn try_push(&mut self, x: u8) -> Result<(), DecodeError> {
+        if self.skip != 0 {
+            self.skip -= 1;
+            return Ok(());
+        }
+
+        // `needed` is filled in by the caller, which has the whole message.
+        *self
+            .data
+            .get_mut(self.len)
+            .ok_or(DecodeError::BufferOverflow { needed: 0 })? = x;
+        self.len += 1;
+
+        Ok(())
+    }
+
+    fn rev(&mut self) {
+        self.data[..self.len].reverse()
+    }
+}
+
+/// Decode a full message into two slices, filling `first` then continuing into `second`.
+///
+/// Returns how many bytes went into each. `second` is only used once `first` is full.
+///
+/// Fails with [DecodeError::BufferOverflow] if the message doesn't fit in both.
+pub fn decode_to_split_slices(
+    data: &[u8],
+    first: &mut [u8],
+    second: &mut [u8],
+) -> Result<(usize, usize), DecodeError> {
+    let needed = decoded_len(data)?;
+    if needed > first.len() + second.len() {
+        return Err(DecodeError::BufferOverflow { needed });
+    }
+
+    let mut res = SplitBuffer {
+        first,
+        second,
+        pos: needed,
+    };
+    decode_helper(data, &mut res)?;
+
+    debug_assert_eq!(res.pos, 0, "decoded length bookkeeping is off");
+    let n = needed.min(res.first.len());
+    Ok((n, needed - n))
+}
+
+/// Two slices filled by the decoder, from the end of the message since the decoder
+/// outputs bytes in reverse order.
+struct SplitBuffer<'a, 'b> {
+    first: &'a mut [u8],
+    second: &'b mut [u8],
+    /// Position in the message of the byte after the next one to write.
+    pos: usize,
+}
+
+impl<'a, 'b> Buffer for SplitBuffer<'a, 'b> {
+    fn try_push(&mut self, x: u8) -> Result<(), DecodeError> {
+        self.pos -= 1;
+        match self.pos.checked_sub(self.first.len()) {
+            Some(i) => self.second[i] = x,
+            None => self.first[self.pos] = x,
+        }
+        Ok(())
+    }
+
+    fn rev(&mut self) {}
+}
+
+/// Decode a full message into a possibly uninitialized buffer.
+///
+/// Same as [decode_to_slice], but skips zeroing the buffer first, which helps for
+/// large buffers. Returns the decoded message, at the start of `res`. Every byte of
+/// it has been written, and the rest of `res` is left untouched.
+pub fn decode_to_uninit<'a>(
+    data: &[u8],
+    res: &'a mut [MaybeUninit<u8>],
+) -> Result<&'a mut [u8], DecodeError> {
+    let needed = decoded_len(data)?;
+    if needed > res.len() {
+        return Err(DecodeError::BufferOverflow { needed });
+    }
+
+    let mut buf = UninitBuffer {
+        data: &mut res[..needed],
+        pos: needed,
+    };
+    decode_helper(data, &mut buf)?;
+
+    // Each push writes the byte before the previous one, so with all `needed` bytes
+    // pushed the whole prefix was written. This must hold for soundness, so it's not
+    // just a debug assertion.
+    assert_eq!(buf.pos, 0, "decoded length bookkeeping is off");
+    // SAFETY: `res[..needed]` is initialized, see above, and `MaybeUninit<u8>` has the
+    // same layout as `u8`.
+    Ok(unsafe { &mut *(&mut res[..needed] as *mut [MaybeUninit<u8>] as *mut [u8]) })
+}
+
+/// Uninitialized slice filled by the decoder, from its end since the decoder outputs
+/// bytes in reverse order.
+struct UninitBuffer<'a> {
+    data: &'a mut [MaybeUninit<u8>],
+    /// Position of the byte after the next one to write.
+    pos: usize,
+}
+
+impl<'a> Buffer for UninitBuffer<'a> {
+    fn try_push(&mut self, x: u8) -> Result<(), DecodeError> {
+        self.pos -= 1;
+        self.data[self.pos] = MaybeUninit::new(x);
+        Ok(())
+    }
+
+    fn rev(&mut self) {}
+}
+
+/// Decode a full message which must have exactly `res.len()` bytes, not counting padding.
+///
+/// Handy for fixed-size messages. Since padding can't be told apart from trailing
+/// zeros in the message, any length between the decoded length without its
+/// padding and the full decoded length is accepted.
+///
+/// Fails with [DecodeError::BufferOverflow] if the message is longer even without its
+/// padding, or [DecodeError::LengthMismatch] if it is shorter.
+pub fn decode_exact_into(data: &[u8], res: &mut [u8]) -> Result<(), DecodeError> {
+    let len = decoded_len(data)?;
+    if res.len() < len - padding_len(data) {
+        return Err(DecodeError::BufferOverflow { needed: len });
+    }
+    if res.len() > len {
+        return Err(DecodeError::LengthMismatch);
+    }
+
+    let skip = len - res.len();
+    let mut res = SliceBuffer {
+        data: res,
+        len: 0,
+        skip,
+    };
+    decode_helper(data, &mut res)
+}
+
+/// Decode a full message into an array, for fixed-size messages.
+///
+/// Same as [decode_exact_into], with an `N` byte array as the output.
+pub fn decode_array<const N: usize>(data: &[u8]) -> Result<[u8; N], DecodeError> {
+    let mut res = [0; N];
+    decode_exact_into(data, &mut res)?;
+    Ok(res)
+}
+
+/// Decode a message into a plain-old-data value.
+///
+/// The decoded message must be exactly `size_of::<T>()` bytes, not counting the
+/// zero padding added by the encoding. A message that is longer fails with
+/// [DecodeError::BufferOverflow], one that is shorter with [DecodeError::LengthMismatch].
+#[cfg(feature = "bytemuck")]
+pub fn decode_to_pod<T: bytemuck::Pod>(data: &[u8]) -> Result<T, DecodeError> {
+    let mut res = T::zeroed();
+    decode_exact_into(data, bytemuck::bytes_of_mut(&mut res))?;
+    Ok(res)
+}
+
+/// Decode a full message into a [`heapless::Vec`].
+///
+/// Same as [decode_to_slice], using the vec's storage as the output buffer. Fails
+/// with [DecodeError::BufferOverflow] if the decoded message is longer than `N`.
+#[cfg(feature = "heapless")]
+pub fn decode_to_heapless<const N: usize>(
+    data: &[u8],
+) -> Result<heapless::Vec<u8, N>, DecodeError> {
+    let mut res = heapless::Vec::new();
+    res.resize(N, 0).unwrap();
+    let len = decode_to_slice(data, &mut res)?.len();
+    res.truncate(len);
+    Ok(res)
+}
+
+/// Decode a full message, reporting how many input bytes were used.
+///
+/// Same as [decode_to_slice], except `data` may also end with a single `0x00`
+/// separator byte. On success, `consumed` is set to the number of bytes of `data`
+/// that were used, including the separator if present. On error, `consumed` is
+/// left untouched.
+pub fn decode_to_slice_consumed<'a>(
+    data: &[u8],
+    res: &'a mut [u8],
+    consumed: &mut usize,
+) -> Result<&'a mut [u8], DecodeError> {
+    let frame = match data.split_last() {
+        Some((0, frame)) => frame,
+        _ => data,
+    };
+    let res = decode_to_slice(frame, res)?;
+    *consumed = data.len();
+    Ok(res)
+}
+
+/// Maximum decoded length for an encoded message of `len` bytes.
+///
+/// Each encoded byte decodes to at most 7 bytes, for a header outputting 7 zeros.
+/// When decoding untrusted data, bound the encoded message length to bound the
+/// memory needed to decode it. Saturates at `usize::MAX`.
+pub const fn max_decoded_len(len: usize) -> usize {
+    len.saturating_mul(7)
+}
+
+/// Compute the length of a decoded message.
+///
+/// Returns the exact length [decode] and [decode_to_slice] would output for `data`,
+/// including the zero padding. This only looks at the headers, so it is much cheaper
+/// than decoding.
+///
+/// It fails on exactly the same messages as [decode_to_slice], so when it succeeds,
+/// decoding into a buffer of the returned length can't fail. See [prepare_decode].
+///
+/// If the length doesn't fit in a `usize`, which is possible for huge messages on
+/// 32-bit targets, fails with [DecodeError::BufferOverflow] with `needed` set to
+/// `usize::MAX`.
+pub fn decoded_len(mut data: &[u8]) -> Result<usize, DecodeError> {
+    let mut len = 0usize;
+    while !data.is_empty() {
+        let (rest, _, header) = split_group(data)?;
+        len = len
+            .checked_add(header_len(header).unwrap().1)
+            .ok_or(DecodeError::BufferOverflow { needed: usize::MAX })?;
+        data = rest;
+    }
+    Ok(len)
+}
+
+/// Kind of a header, see [HeaderInfo].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderKind {
+    /// `0x01..=0x7f`: a group of 7 bytes, with the zeros given by the header bits.
+    ZeroGroup,
+    /// `0x80..=0xfe`: a run of literal bytes followed by a zero.
+    Run,
+    /// `0xff`: a run of 134 literal bytes.
+    MaxRun,
+}
+
+/// What a header byte does when decoding, as returned by [decode_header].
+///
+/// Headers come after the stream bytes they use, so decoding walks both the stream
+/// and the output backwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderOp {
+    /// `0x00`: end of frame.
+    EndOfFrame,
+    /// `0x01..=0x7f`: output 7 bytes. For each bit of `zeros` from LSB to MSB, if it
+    /// is 1 output `0x00`, else output 1 byte from the stream.
+    ZeroGroup { zeros: u8 },
+    /// `0x80..=0xfe`: output `literals` bytes from the stream, then `0x00`.
+    /// `literals` is the low 7 bits plus 7.
+    Run { literals: usize },
+    /// `0xff`: output 134 bytes from the stream.
+    MaxRun,
+}
+
+/// Describe what a header byte does when decoding.
+///
+/// This is the reference for the format's header semantics.
+pub const fn decode_header(header: u8) -> HeaderOp {
+    match header {
+        0x00 => HeaderOp::EndOfFrame,
+        0x01..=0x7f => HeaderOp::ZeroGroup { zeros: header },
+        0x80..=0xfe => HeaderOp::Run {
+            literals: (header & 0x7f) as usize + 7,
+        },
+        0xff => HeaderOp::MaxRun,
+    }
+}
+
+/// Description of a header in an encoded message, as returned by [inspect].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeaderInfo {
+    /// Position of the header byte in the encoded message.
+    pub pos: usize,
+    /// The header byte.
+    pub header: u8,
+    pub kind: HeaderKind,
+    /// Number of literal bytes before the header that belong to its group.
+    pub consumed: usize,
+    /// Number of decoded bytes the group outputs.
+    pub produced: usize,
+}
+
+/// List the headers of an encoded message, in stream order.
+///
+/// This is meant for debugging encoders: it shows how the message is split in
+/// groups, without decoding it. It fails on the same messages as [decode_to_slice].
+#[cfg(feature = "alloc")]
+pub fn inspect(mut data: &[u8]) -> Result<Vec<HeaderInfo>, DecodeError> {
+    let mut res = Vec::new();
+    while !data.is_empty() {
+        let (rest, literals, header) = split_group(data)?;
+        let (consumed, produced) = header_len(header).unwrap();
+        let kind = match header {
+            0x01..=0x7f => HeaderKind::ZeroGroup,
+            0x80..=0xfe => HeaderKind::Run,
+            _ => HeaderKind::MaxRun,
+        };
+        res.push(HeaderInfo {
+            pos: rest.len() + literals.len(),
+            header,
+            kind,
+            consumed,
+            produced,
+        });
+        data = rest;
+    }
+    res.reverse();
+    Ok(res)
+}
+
+/// Check whether `data` could be an rzCOBS encoded message.
+///
+/// This is a heuristic, meant to tell rzCOBS streams apart from other data, for
+/// example when sniffing a capture. It checks that `data` has no `0x00` bytes and
+/// that its headers fit, which is enough for it to decode, but says nothing about
+/// whether the decoded content is meaningful. Short random data often passes, and
+/// so does empty data, which is the encoding of an empty message.
+pub fn looks_like_rzcobs(data: &[u8]) -> bool {
+    !data.contains(&0) && decoded_len(data).is_ok()
+}
+
+/// Validate a message and compute the buffer size needed to decode it, in one scan.
+///
+/// This is [decoded_len], named for the pattern of allocating exactly once, then
+/// decoding with [decode_to_slice], which is then guaranteed to succeed and fill
+/// the whole buffer.
+pub fn prepare_decode(data: &[u8]) -> Result<usize, DecodeError> {
+    decoded_len(data)
+}
+
+/// Split the last group off a non-empty message.
+///
+/// Returns the rest of the message, the group's literal stream bytes, and its header.
+fn split_group(data: &[u8]) -> Result<(&[u8], &[u8], u8), DecodeError> {
+    let (&header, data) = data.split_last().unwrap();
+    let (consumed, _) = header_len(header).ok_or(DecodeError::MalformedError)?;
+    if consumed > data.len() {
+        return Err(DecodeError::Truncated {
+            needed: consumed,
+            found: data.len(),
+        });
+    }
+    let (rest, literals) = data.split_at(data.len() - consumed);
+    #[cfg(feature = "strict")]
+    if literals.contains(&0) {
+        return Err(DecodeError::NonCanonical);
+    }
+    Ok((rest, literals, header))
+}
+
+/// Number of trailing zero bytes of the decoded message that may be padding.
+///
+/// These are the trailing zeros output by the last header. Padding can't be told
+/// apart from zeros that were really in the message, so all of them count.
+fn padding_len(data: &[u8]) -> usize {
+    match data.last() {
+        Some(&x @ 0x01..=0x7f) => (x << 1).leading_ones() as usize,
+        Some(0x80..=0xfe) => 1,
+        _ => 0,
+    }
+}
+
+/// Number of stream bytes consumed and output bytes produced by a header,
+/// or `None` if the header is invalid.
+fn header_len(x: u8) -> Option<(usize, usize)> {
+    match x {
+        0 => None,
+        0x01..=0x7f => Some((7 - x.count_ones() as usize, 7)),
+        0x80..=0xfe => {
+            let n = (x & 0x7f) as usize + 7;
+            Some((n, n + 1))
+        }
+        0xff => Some((134, 134)),
+    }
+}
+
+/// Whether output byte `k` of the group with `header` comes from a stream byte.
+fn is_literal(header: u8, k: usize) -> bool {
+    match header {
+        0x01..=0x7f => header & (1 << k) == 0,
+        // The trailing 0x00 of a run is just past its literals.
+        _ => k < header_len(header).unwrap().0,
+    }
+}
+
+/// Output byte `k` of the group formed by `header` and its `literals` stream bytes.
+fn group_byte(header: u8, literals: &[u8], k: usize) -> u8 {
+    match header {
+        0x01..=0x7f if header & (1 << k) != 0 => 0,
+        0x01..=0x7f => literals[(!header & ((1 << k) - 1)).count_ones() as usize],
+        // The trailing 0x00 of a run is just past its literals.
+        _ => literals.get(k).copied().unwrap_or(0),
+    }
+}
+
+/// Decode the part of a message starting at `offset` into `out`.
+///
+/// This walks the headers from the end of the message up to `offset`, so unlike
+/// [decode_helper] it can output a part of the message in forward order, without
+/// decoding what comes after it.
+///
+/// `len` must be the [decoded_len] of `data`, which implies `data` is valid, and
+/// `offset + out.len()` must not exceed it.
+fn decode_window(data: &[u8], len: usize, offset: usize, out: &mut [u8]) {
+    let mut i = data.len();
+    let mut end = len;
+    while end > offset {
+        i -= 1;
+        let header = data[i];
+        let (consumed, produced) = header_len(header).unwrap();
+        i -= consumed;
+        let literals = &data[i..][..consumed];
+
+        let start = end - produced;
+        for pos in start.max(offset)..end.min(offset + out.len()) {
+            out[pos - offset] = group_byte(header, literals, pos - start);
+        }
+        end = start;
+    }
+}
+
+/// Decode a full message, passing it to `f` in chunks of `N` bytes.
+///
+/// The chunks are passed in order, and all are `N` bytes long except possibly
+/// the last one. Only a scratch buffer of `N` bytes is needed, no matter how long
+/// the message is.
+///
+/// The message is validated before calling `f` for the first time, so `f` never
+/// sees part of a malformed message. Since the message can only be decoded from
+/// its end, each chunk walks the headers again from the end of the message: the
+/// decoding time is proportional to the message length times the number of chunks.
+///
+/// Panics if `N` is zero.
+pub fn decode_chunked<const N: usize, E, F>(data: &[u8], mut f: F) -> Result<(), E>
+where
+    E: From<DecodeError>,
+    F: FnMut(&[u8]) -> Result<(), E>,
+{
+    assert!(N != 0, "chunk size must be non-zero");
+    let len = decoded_len(data)?;
+    let mut buf = [0; N];
+    let mut offset = 0;
+    while offset < len {
+        let chunk = &mut buf[..N.min(len - offset)];
+        decode_window(data, len, offset, chunk);
+        f(chunk)?;
+        offset += chunk.len();
+    }
+    Ok(())
+}
+
+/// Decode a full message, passing each byte to `f` in order.
+///
+/// No output buffer is needed. Like [decode_chunked], the message is validated
+/// with a first scan of its headers before `f` is called, so `f` never sees part
+/// of a malformed message.
+///
+/// Messages can only be decoded from their end, so producing bytes in forward
+/// order means walking the headers again from the end for each block of 64 bytes,
+/// using a 64 byte scratch buffer on the stack. That is, decoding time grows with
+/// the square of the message length. For long messages, prefer [decode_chunked]
+/// with a bigger buffer if you can spare the RAM.
+pub fn decode_forward_with<F: FnMut(u8)>(data: &[u8], mut f: F) -> Result<(), DecodeError> {
+    decode_chunked::<64, DecodeError, _>(data, |chunk| {
+        chunk.iter().for_each(|&b| f(b));
+        Ok(())
+    })
+}
+
+/// Decode a full message into a hasher, without an output buffer.
+///
+/// Each decoded byte is passed in order to [Hasher::write_u8](core::hash::Hasher::write_u8),
+/// including the padding. Like [decode_forward_with], which this is built on, the
+/// message is validated before hashing, and decoding time grows with the square of
+/// the message length.
+pub fn decode_hash<H: core::hash::Hasher>(data: &[u8], hasher: &mut H) -> Result<(), DecodeError> {
+    decode_forward_with(data, |b| hasher.write_u8(b))
+}
+
+/// Error returned by [decode_to_writer].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeWriteError<E> {
+    /// The message is invalid. Nothing was written.
+    Decode(DecodeError),
+    /// The writer failed.
+    Writer(E),
+}
+
+impl<E> From<DecodeError> for DecodeWriteError<E> {
+    fn from(e: DecodeError) -> Self {
+        Self::Decode(e)
+    }
+}
+
+/// Decode a full message into a [Write], the same sink trait used by [Encoder].
+///
+/// This allows any writer supporting encoding to be used for decoding too. The
+/// message is validated first, so on [DecodeWriteError::Decode] nothing was written.
+///
+/// Writers can only append, while messages can only be decoded from their end,
+/// so this has the same quadratic cost as [decode_forward_with]. That's why
+/// [decode] and [decode_to_slice], which can fill their output from the end, don't
+/// go through a writer.
+pub fn decode_to_writer<W: Write>(data: &[u8], mut w: W) -> Result<(), DecodeWriteError<W::Error>> {
+    decode_chunked::<64, _, _>(data, |chunk| {
+        for &b in chunk {
+            w.write(b).map_err(DecodeWriteError::Writer)?;
+        }
+        Ok(())
+    })
+}
+
+/// Decode a full message into a [Write] in linear time, with a `WINDOW` byte buffer.
+///
+/// Same as [decode_to_writer], but without its quadratic cost. A first pass walks
+/// the headers from the end of the message, validating it and recording where
+/// each window of up to `WINDOW` decoded bytes starts. Then each window is decoded
+/// into a buffer of `WINDOW` bytes on the stack, and written out in order. A group
+/// decoding to more than `WINDOW` bytes, at most 135, is written straight from the
+/// message instead.
+///
+/// Memory use is the `WINDOW` byte buffer, plus one `usize` per window on the heap.
+/// Time is linear in the message length for any `WINDOW`, but a small window means
+/// more recorded windows, and more groups written one byte at a time.
+#[cfg(feature = "alloc")]
+pub fn decode_to_writer_windowed<W: Write, const WINDOW: usize>(
+    data: &[u8],
+    mut w: W,
+) -> Result<(), DecodeWriteError<W::Error>> {
+    // Window boundaries, from the end. Each window is made of whole groups.
+    let mut bounds = alloc::vec![data.len()];
+    let mut rest = data;
+    let mut produced = 0;
+    while !rest.is_empty() {
+        let (r, _, x) = split_group(rest)?;
+        let (_, n) = header_len(x).unwrap();
+        if produced != 0 && produced + n > WINDOW {
+            bounds.push(rest.len());
+            produced = 0;
+        }
+        produced += n;
+        rest = r;
+    }
+    if bounds.last() != Some(&0) {
+        bounds.push(0);
+    }
+
+    let mut buf = [0; WINDOW];
+    for win in bounds.windows(2).rev() {
+        let piece = &data[win[1]..win[0]];
+        let (r, literals, x) = split_group(piece)?;
+        let (_, n) = header_len(x).unwrap();
+        if r.is_empty() && n > WINDOW {
+            for k in 0..n {
+                w.write(group_byte(x, literals, k))
+                    .map_err(DecodeWriteError::Writer)?;
+            }
+        } else {
+            for &b in decode_to_slice(piece, &mut buf)?.iter() {
+                w.write(b).map_err(DecodeWriteError::Writer)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Decode a message too large to fit in memory, reading it from a seekable reader.
+///
+/// `r` holds the message in its first `len` bytes, without its `0x00` separator.
+/// The decoded message is written to `out` in order. Malformed messages fail
+/// with [std::io::ErrorKind::InvalidData], before anything is written, except with
+/// the `strict` feature, where a [DecodeError::NonCanonical] message may be
+/// detected after part of it was written.
+///
+/// Messages can only be decoded from their end, so this reads `r` twice. First
+/// backwards, one 4 KiB chunk at a time, seeking before each one, to find the group
+/// boundaries. It keeps one boundary per 64 KiB of decoded data, so memory use
+/// grows with the message, but slowly. Then forwards, decoding the message 64 KiB
+/// at a time, seeking to each piece.
+#[cfg(feature = "std")]
+pub fn decode_from_seek<R: std::io::Read + std::io::Seek>(
+    r: &mut R,
+    len: u64,
+    out: &mut impl std::io::Write,
+) -> std::io::Result<()> {
+    use std::io::SeekFrom;
+
+    const CHUNK: u64 = 4096;
+    const PIECE: usize = 65536;
+
+    let invalid = |e: DecodeError| {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, alloc::format!("{:?}", e))
+    };
+
+    // Group boundaries, from the end, each starting a piece.
+    let mut bounds = alloc::vec![len];
+    let mut buf = [0; CHUNK as usize];
+    // Stream offset of `buf[0]`, and number of valid bytes in `buf`.
+    let mut buf_start = 0;
+    let mut buf_len = 0;
+    let mut pos = len;
+    let mut produced = 0;
+    while pos > 0 {
+        let header_pos = pos - 1;
+        if header_pos < buf_start || header_pos >= buf_start + buf_len {
+            buf_start = pos.saturating_sub(CHUNK);
+            buf_len = pos - buf_start;
+            r.seek(SeekFrom::Start(buf_start))?;
+            r.read_exact(&mut buf[..buf_len as usize])?;
+        }
+        let header = buf[(header_pos - buf_start) as usize];
+        let (consumed, n) =
+            header_len(header).ok_or_else(|| invalid(DecodeError::MalformedError))?;
+        pos = header_pos.checked_sub(consumed as u64).ok_or_else(|| {
+            invalid(DecodeError::Truncated {
+                needed: consumed,
+                found: header_pos as usize,
+            })
+        })?;
+        produced += n;
+        if produced >= PIECE {
+            bounds.push(pos);
+            produced = 0;
+        }
+    }
+    if bounds.last() != Some(&0) {
+        bounds.push(0);
+    }
+
+    // Each piece is made of whole groups, so it decodes on its own.
+    let mut piece = Vec::new();
+    for w in bounds.windows(2).rev() {
+        let (end, start) = (w[0], w[1]);
+        piece.resize((end - start) as usize, 0);
+        r.seek(SeekFrom::Start(start))?;
+        r.read_exact(&mut piece)?;
+        out.write_all(&decode_vec(&piece).map_err(invalid)?)?;
+    }
+    Ok(())
+}
+
+/// Decoder for a message received in chunks, last chunk first.
+///
+/// Messages are decoded from their end, so a message received back to front can
+/// be decoded as it arrives. Call [feed_reverse](Self::feed_reverse) with each
+/// chunk, starting with the one holding the end of the message, then
+/// [finish](Self::finish). The bytes within each chunk are in stream order:
+/// concatenating the chunks in the opposite order they were fed gives the message.
+/// The message must not include its `0x00` separator byte.
+///
+/// The output buffer is filled from its end, so the decoded message comes out in
+/// forward order without being reversed.
+pub struct ReverseChunkDecoder<'a> {
+    out: &'a mut [u8],
+    /// Decoded length so far. It keeps counting after overflowing `out`,
+    /// to report the needed length.
+    len: usize,
+    /// Header of the current group.
+    header: u8,
+    /// Number of bytes of the current group not output yet.
+    pos: usize,
+}
+
+impl<'a> ReverseChunkDecoder<'a> {
+    /// Create a decoder writing to `out`.
+    pub fn new(out: &'a mut [u8]) -> Self {
+        Self {
+            out,
+            len: 0,
+            header: 0,
+            pos: 0,
+        }
+    }
+
+    /// Decode the chunk of the message preceding the chunks fed so far.
+    pub fn feed_reverse(&mut self, chunk: &[u8]) -> Result<(), DecodeError> {
+        let mut i = chunk.len();
+        loop {
+            while self.pos > 0 {
+                let b = if is_literal(self.header, self.pos - 1) {
+                    if i == 0 {
+                        return Ok(());
+                    }
+                    i -= 1;
+                    #[cfg(feature = "strict")]
+                    if chunk[i] == 0 {
+                        return Err(DecodeError::NonCanonical);
+                    }
+                    chunk[i]
+                } else {
+                    0
+                };
+                self.push(b);
+                self.pos -= 1;
+            }
+
+            if i == 0 {
+                return Ok(());
+            }
+            i -= 1;
+            self.header = chunk[i];
+            self.pos = header_len(self.header)
+                .ok_or(DecodeError::MalformedError)?
+                .1;
+        }
+    }
+
+    fn push(&mut self, b: u8) {
+        // Saturating, so that feeding a huge stream can't panic. If it saturates,
+        // `finish` fails with an overflow anyway.
+        self.len = self.len.saturating_add(1);
+        if let Some(i) = self.out.len().checked_sub(self.len) {
+            self.out[i] = b;
+        }
+    }
+
+    /// Finish decoding, returning the decoded message.
+    pub fn finish(self) -> Result<&'a mut [u8], DecodeError> {
+        if self.pos > 0 {
+            let (needed, _) = header_len(self.header).unwrap();
+            let missing = (0..self.pos)
+                .filter(|&k| is_literal(self.header, k))
+                .count();
+            return Err(DecodeError::Truncated {
+                needed,
+                found: needed - missing,
+            });
+        }
+        if self.len > self.out.len() {
+            return Err(DecodeError::BufferOverflow { needed: self.len });
+        }
+        let start = self.out.len() - self.len;
+        Ok(&mut self.out[start..])
+    }
+}
+
+/// Decode a full message into a `Vec`.
+///
+/// Unlike [decode_helper], this sizes the output with [decoded_len] first, then
+/// fills it from the end. Literal runs are then copied as whole slices, which is
+/// much faster for messages with few zeros.
+#[cfg(feature = "alloc")]
+fn decode_vec(data: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let len = decoded_len(data)?;
+    // Larger allocations always fail, with a panic.
+    if len > isize::MAX as usize {
+        return Err(DecodeError::BufferOverflow { needed: len });
+    }
+    let mut res = alloc::vec![0; len];
+    let mut data = data;
+    let mut end = res.len();
+    while !data.is_empty() {
+        let (rest, literals, x) = split_group(data)?;
+        data = rest;
+
+        let (_, produced) = header_len(x).unwrap();
+        let group = &mut res[end - produced..end];
+        end -= produced;
+        match x {
+            0x01..=0x7f => {
+                let mut literals = literals.iter().cloned();
+                for (i, b) in group.iter_mut().enumerate() {
+                    if x & (1 << i) == 0 {
+                        *b = literals.next().unwrap();
+                    }
+                }
+            }
+            // The rest of the group is already zero.
+            _ => group[..literals.len()].copy_from_slice(literals),
+        }
+    }
+
+    debug_assert_eq!(end, 0, "decoded length bookkeeping is off");
+    Ok(res)
+}
+
+fn decode_helper(mut data: &[u8], dst: &mut impl Buffer) -> Result<(), DecodeError> {
+    while !data.is_empty() {
+        let (rest, literals, x) = split_group(data)?;
+        data = rest;
+
+        let mut literals = literals.iter().rev().cloned();
+        match x {
+            0x01..=0x7f => {
+                for i in 0..7 {
+                    if x & (1 << (6 - i)) == 0 {
+                        dst.try_push(literals.next().unwrap())?;
+                    } else {
+                        dst.try_push(0)?;
+                    }
+                }
+            }
+            0x80..=0xfe => {
+                dst.try_push(0)?;
+                for b in literals {
+                    dst.try_push(b)?;
+                }
+            }
+            _ => {
+                for b in literals {
+                    dst.try_push(b)?;
+                }
+            }
+        }
+    }
+
+    dst.rev();
+    Ok(())
+}
+
+// rCOBS headers are like rzCOBS run headers: `n` is n-1 stream bytes then a 0x00,
+// and 0xff is 254 stream bytes. The last header's 0x00 is not part of the message.
+#[cfg(feature = "alloc")]
+fn decode_rcobs_helper(mut data: &[u8], dst: &mut impl Buffer) -> Result<(), DecodeError> {
+    let mut last = true;
+    while let Some((&x, rest)) = data.split_last() {
+        let consumed = match x {
+            0 => return Err(DecodeError::MalformedError),
+            0xff => 254,
+            _ => x as usize - 1,
+        };
+        if consumed > rest.len() {
+            return Err(DecodeError::Truncated {
+                needed: consumed,
+                found: rest.len(),
+            });
+        }
+        if x != 0xff && !last {
+            dst.try_push(0)?;
+        }
+        let (rest, literals) = rest.split_at(rest.len() - consumed);
+        for &b in literals.iter().rev() {
+            dst.try_push(b)?;
+        }
+        data = rest;
+        last = false;
+    }
+
+    dst.rev();
+    Ok(())
+}
+
+/// Codec for [asynchronous-codec](https://docs.rs/asynchronous-codec).
+///
+/// Use it with `FramedRead`/`FramedWrite` to send and receive messages over any
+/// `AsyncRead`/`AsyncWrite`, such as an async-std `TcpStream`.
+///
+/// Encoding writes the encoded message followed by a `0x00` separator byte.
+/// Decoding yields one decoded message per `0x00` separator.
+///
+/// The decoder buffers incoming data until a separator shows up, so the maximum
+/// encoded message length is mandatory, to bound memory usage when the peer never
+/// sends a separator. Longer messages fail with [DecodeError::FrameTooLong], and
+/// are discarded up to their separator.
+#[cfg(feature = "asynchronous-codec")]
+#[derive(Debug, Clone)]
+pub struct RzcobsCodec {
+    max_frame_bytes: usize,
+    /// A message too long was reported, and its rest must be discarded.
+    discarding: bool,
+}
+
+#[cfg(feature = "asynchronous-codec")]
+impl RzcobsCodec {
+    /// Create a codec decoding messages of up to `max_frame_bytes` encoded bytes,
+    /// not counting the separator.
+    pub const fn new(max_frame_bytes: usize) -> Self {
+        Self {
+            max_frame_bytes,
+            discarding: false,
+        }
+    }
+}
+
+/// Error returned by [RzcobsCodec].
+#[cfg(feature = "asynchronous-codec")]
+#[derive(Debug)]
+pub enum CodecError {
+    Io(std::io::Error),
+    Decode(DecodeError),
+}
+
+#[cfg(feature = "asynchronous-codec")]
+impl From<std::io::Error> for CodecError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+#[cfg(feature = "asynchronous-codec")]
+impl asynchronous_codec::Encoder for RzcobsCodec {
+    type Item<'a> = &'a [u8];
+    type Error = CodecError;
+
+    fn encode(
+        &mut self,
+        item: Self::Item<'_>,
+        dst: &mut asynchronous_codec::BytesMut,
+    ) -> Result<(), Self::Error> {
+        struct BytesWriter<'a>(&'a mut asynchronous_codec::BytesMut);
+
+        impl<'a> Write for BytesWriter<'a> {
+            type Error = core::convert::Infallible;
+            fn write(&mut self, byte: u8) -> Result<(), Self::Error> {
+                self.0.extend_from_slice(&[byte]);
+                Ok(())
+            }
+        }
+
+        dst.reserve(max_encoded_len(item.len()) + 1);
+        let mut enc = Encoder::new(BytesWriter(dst));
+        for &b in item {
+            enc.write(b).unwrap();
+        }
+        enc.end().unwrap();
+        enc.writer().write(0).unwrap();
+        Ok(())
+    }
+}
+
+#[cfg(feature = "asynchronous-codec")]
+impl asynchronous_codec::Decoder for RzcobsCodec {
+    type Item = Vec<u8>;
+    type Error = CodecError;
+
+    fn decode(
+        &mut self,
+        src: &mut asynchronous_codec::BytesMut,
+    ) -> Result<Option<Self::Item>, Self::Error> {
+        loop {
+            let len = match src.iter().position(|&b| b == 0) {
+                Some(len) => len,
+                None if self.discarding => {
+                    src.clear();
+                    return Ok(None);
+                }
+                None if src.len() > self.max_frame_bytes => {
+                    src.clear();
+                    self.discarding = true;
+                    return Err(CodecError::Decode(DecodeError::FrameTooLong));
+                }
+                None => return Ok(None),
+            };
+            let frame = src.split_to(len + 1);
+            if self.discarding {
+                self.discarding = false;
+                continue;
+            }
+            if len > self.max_frame_bytes {
+                return Err(CodecError::Decode(DecodeError::FrameTooLong));
+            }
+            return decode_vec(&frame[..len])
+                .map(Some)
+                .map_err(CodecError::Decode);
+        }
+    }
+}
+
+/// Fuzz target body checking the encode/decode roundtrip.
+///
+/// Encodes `data`, decodes it back with both [decode] and [decode_to_slice]
+/// and panics if any of the invariants guaranteed by this crate is violated:
+/// the encoded message contains no `0x00`, and the decoded message is `data`
+/// followed by at most 6 zero bytes of padding.
+#[cfg(feature = "test-util")]
+pub fn fuzz_roundtrip(data: &[u8]) {
+    let enc = encode(data);
+    assert!(!enc.contains(&0), "encoded message contains 0x00");
+
+    let dec = decode(&enc).expect("decoding an encoded message failed");
+    assert_eq!(&dec[..data.len()], data, "decoded message differs");
+    let padding = &dec[data.len()..];
+    assert!(
+        padding.len() <= 6,
+        "too much padding: {} bytes",
+        padding.len()
+    );
+    assert!(padding.iter().all(|&x| x == 0), "padding is not zero");
+
+    let mut buf = vec![0; dec.len()];
+    let dec_slice = decode_to_slice(&enc, &mut buf).expect("decode_to_slice failed");
+    assert_eq!(dec_slice, &dec[..], "decode and decode_to_slice differ");
+}
+
+/// Describe the differences between two encoded messages.
+///
+/// Returns a side-by-side hex dump of `expected` and `got`, with rows containing
+/// differences marked with `>`, followed by the result of decoding each of them.
+/// Meant for test failure messages, for example when validating another encoder
+/// implementation against this crate.
+#[cfg(feature = "test-util")]
+pub fn debug_compare(expected: &[u8], got: &[u8]) -> String {
+    use std::fmt::Write as _;
+
+    fn row(s: &mut String, data: &[u8], start: usize) {
+        for i in start..start + 8 {
+            match data.get(i) {
+                Some(x) => write!(s, "{:02x} ", x).unwrap(),
+                None => s.push_str("   "),
+            }
+        }
+    }
+
+    fn decoded(s: &mut String, name: &str, data: &[u8]) {
+        write!(s, "{} decodes to: ", name).unwrap();
+        match decode(data) {
+            Ok(dec) => dec.iter().for_each(|x| write!(s, "{:02x}", x).unwrap()),
+            Err(MalformedError) => s.push_str("malformed"),
+        }
+        s.push('\n');
+    }
+
+    let mut s = String::new();
+    let len = expected.len().max(got.len());
+    match (0..len).find(|&i| expected.get(i) != got.get(i)) {
+        Some(i) => writeln!(s, "first difference at byte {}", i).unwrap(),
+        None => writeln!(s, "no difference").unwrap(),
+    }
+    writeln!(s, "       {:24} | got", "expected").unwrap();
+    for start in (0..len).step_by(8) {
+        let diff = (start..start + 8).any(|i| expected.get(i) != got.get(i));
+        write!(s, "{} {:04x} ", if diff { '>' } else { ' ' }, start).unwrap();
+        row(&mut s, expected, start);
+        s.push_str("| ");
+        row(&mut s, got, start);
+        s.truncate(s.trim_end().len());
+        s.push('\n');
+    }
+    decoded(&mut s, "expected", expected);
+    decoded(&mut s, "got", got);
+    s
+}
+
+/// Fuzz target body checking decoding of arbitrary data.
+///
+/// Decodes `data` with both [decode] and [decode_to_slice], which must not
+/// panic for any input, and panics if their results disagree.
+#[cfg(feature = "test-util")]
+pub fn fuzz_decode(data: &[u8]) {
+    let dec = decode(data);
+
+    let mut buf = [0; 1024];
+    match decode_to_slice(data, &mut buf) {
+        Ok(got) => assert_eq!(
+            Ok(&got[..]),
+            dec.as_deref(),
+            "decode and decode_to_slice differ"
+        ),
+        Err(
+            DecodeError::MalformedError | DecodeError::Truncated { .. } | DecodeError::NonCanonical,
+        ) => {
+            assert!(dec.is_err(), "decode_to_slice failed but decode succeeded")
+        }
+        Err(DecodeError::BufferOverflow { needed }) => {
+            let dec = dec.expect("decode_to_slice overflowed but decode failed");
+            assert_eq!(needed, dec.len(), "wrong needed length");
+            assert!(
+                needed > buf.len(),
+                "decode_to_slice overflowed a large enough buffer"
+            );
+        }
+        Err(e) => panic!("unexpected error {:?}", e),
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+
+    /// Xorshift generator, for reproducible random test data.
+    fn rng(mut seed: u64) -> impl FnMut() -> u64 {
+        move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        }
+    }
+
+    /// Message with zero groups, and a run longer than 134 bytes.
+    fn sample_message() -> Vec<u8> {
+        let mut data = vec![0; 20];
+        data.extend((1..=200).map(|x| x as u8));
+        data
+    }
+
+    /// [sample_message], followed by zero groups with a few literals.
+    fn sample_message_mixed() -> Vec<u8> {
+        let mut data = sample_message();
+        data.extend(hex!("00000000004400 000000000000ff 01"));
+        data
+    }
+
+    #[test]
+    fn it_works() {
+        let tests: &[(&[u8], &[u8])] = &[
+            (
+                &hex!(""),
+                &hex!(""),
+            ), (
+                &hex!("00"),
+                &hex!("7f"),
+            ), (
+                &hex!("0000"),
+                &hex!("7f"),
+            ), (
+                &hex!("00000000000000"),
+                &hex!("7f"),
+            ), (
+                &hex!("0000000000000000"),
+                &hex!("7f7f"),
+            ), (
+                &hex!("01"),
+                &hex!("017e"),
+            ), (
+                &hex!("0100"),
+                &hex!("017e"),
+            ), (
+                &hex!("0001"),
                 &hex!("017d"),
             ), (
                 &hex!("0102"),
@@ -300,17 +3748,2312 @@ mod tests {
                 &hex!("0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f404142434445464748494a4b4c4d4e4f505152535455565758595a5b5c5d5e5f606162636465666768696a6b6c6d6e6f707172737475767778797a7b7c7d7e7f80818283848586ff7f"),
             ),
         ];
-        let mut scratch = [0; 141];
-        for (dec, enc) in tests {
-            assert_eq!(&encode(dec), enc);
+        let mut scratch = [0; 141];
+        for (dec, enc) in tests {
+            assert_eq!(&encode(dec), enc);
+
+            let got = decode(enc).unwrap();
+            assert_eq!(&got[..dec.len()], *dec);
+            assert!(&got[dec.len()..].iter().all(|&x| x == 0));
+
+            let got_slice = decode_to_slice(enc, &mut scratch).unwrap();
+            assert_eq!(&got_slice[..dec.len()], *dec);
+            assert!(&got_slice[dec.len()..].iter().all(|&x| x == 0));
+        }
+    }
+
+    #[test]
+    fn chunk_writer() {
+        let data = hex!("0102030405060708090a 0000000000000000 1122334455667788 00");
+        let expected = encode(&data);
+
+        let mut chunks: Vec<Vec<u8>> = Vec::new();
+        {
+            let mut enc = Encoder::new(ChunkWriter::<_, 4>::new(|chunk: &[u8]| {
+                chunks.push(chunk.to_vec());
+                Ok::<(), ()>(())
+            }));
+            for &b in &data {
+                enc.write(b).unwrap();
+            }
+            enc.end().unwrap();
+            enc.writer().flush().unwrap();
+        }
+
+        assert!(chunks[..chunks.len() - 1].iter().all(|c| c.len() == 4));
+        assert_eq!(chunks.concat(), expected);
+    }
+
+    #[cfg(feature = "test-util")]
+    #[test]
+    fn fuzz_helpers() {
+        let inputs: &[&[u8]] = &[
+            &hex!(""),
+            &hex!("00"),
+            &hex!("0001"),
+            &hex!("00000000004400 000000000000ff"),
+            &hex!("1122334455667700"),
+            &[0xff; 300],
+        ];
+        for data in inputs {
+            fuzz_roundtrip(data);
+            fuzz_decode(data);
+            fuzz_decode(&encode(data));
+        }
+    }
+
+    #[test]
+    fn buffer_overflow_needed() {
+        let enc = [0x7f; 100];
+        let mut buf = [0; 10];
+        assert_eq!(
+            decode_to_slice(&enc, &mut buf),
+            Err(DecodeError::BufferOverflow { needed: 700 })
+        );
+        assert_eq!(decoded_len(&enc), Ok(700));
+
+        let mut buf = [0; 700];
+        assert!(decode_to_slice(&enc, &mut buf)
+            .unwrap()
+            .iter()
+            .all(|&x| x == 0));
+
+        // Malformed frames report the error even if they overflow first.
+        let mut enc = [0x7f; 100];
+        enc[0] = 0x01;
+        let mut buf = [0; 10];
+        assert_eq!(
+            decode_to_slice(&enc, &mut buf),
+            Err(DecodeError::Truncated {
+                needed: 6,
+                found: 0
+            })
+        );
+    }
+
+    #[test]
+    fn decode_consumed() {
+        let mut buf = [0; 16];
+        let mut consumed = 0;
+        let got = decode_to_slice_consumed(&hex!("01027c"), &mut buf, &mut consumed).unwrap();
+        assert_eq!(got, &hex!("01020000000000"));
+        assert_eq!(consumed, 3);
+
+        let mut consumed = 0;
+        let got = decode_to_slice_consumed(&hex!("01027c00"), &mut buf, &mut consumed).unwrap();
+        assert_eq!(got, &hex!("01020000000000"));
+        assert_eq!(consumed, 4);
+
+        let mut consumed = 0;
+        assert_eq!(
+            decode_to_slice_consumed(&hex!("7c00"), &mut buf, &mut consumed),
+            Err(DecodeError::Truncated {
+                needed: 2,
+                found: 0
+            })
+        );
+        assert_eq!(consumed, 0);
+    }
+
+    #[test]
+    fn encode_slice() {
+        let mut buf = [0; 16];
+        assert_eq!(
+            encode_to_slice(&hex!("0102"), &mut buf),
+            Ok(&mut hex!("01027c")[..])
+        );
+        assert_eq!(
+            encode_to_slice(&hex!("0102"), &mut buf[..2]),
+            Err(BufferOverflowError)
+        );
+
+        for data in [&[0xff; 300][..], &[0; 300][..], &hex!("1122334455667788")] {
+            let mut buf = vec![0; max_encoded_len(data.len())];
+            assert_eq!(encode_to_slice(data, &mut buf).unwrap(), &encode(data)[..]);
+        }
+
+        let (buf, len) = rzcobs_encode!(hex!("1122334455667788"));
+        assert_eq!(buf.len(), 9);
+        assert_eq!(&buf[..len], &hex!("112233445566778881"));
+
+        let data = [0xff; 134];
+        let (buf, len) = rzcobs_encode!(data; 134);
+        assert_eq!(&buf[..len], &encode(&data)[..]);
+    }
+
+    #[test]
+    fn retry_after_writer_error() {
+        // Fails every third write.
+        struct Flaky(Vec<u8>, usize);
+        impl Write for Flaky {
+            type Error = ();
+            fn write(&mut self, byte: u8) -> Result<(), ()> {
+                self.1 += 1;
+                if self.1.is_multiple_of(3) {
+                    return Err(());
+                }
+                self.0.push(byte);
+                Ok(())
+            }
+        }
+
+        let data = sample_message_mixed();
+
+        let mut enc = Encoder::new(Flaky(Vec::new(), 0));
+        for &b in &data {
+            while enc.write(b).is_err() {}
+        }
+        while enc.end().is_err() {}
+        assert_eq!(enc.writer().0, encode(&data));
+
+        while enc.write(0x11).is_err() {}
+        enc.abort();
+        enc.writer().0.clear();
+        for &b in &data {
+            while enc.write(b).is_err() {}
+        }
+        while enc.end().is_err() {}
+        assert_eq!(enc.writer().0, encode(&data));
+    }
+
+    #[test]
+    fn map_writer() {
+        let data = hex!("00000000004400 000000000000ff 0102");
+        let mut buf = [0; 32];
+        let mut enc = Encoder::new(MapWriter::new(SliceWriter::new(&mut buf), |x| x ^ 0x5a));
+        for &b in &data {
+            enc.write(b).unwrap();
+        }
+        enc.end().unwrap();
+        let len = enc.writer().writer().written();
+
+        let mut received = buf[..len].to_vec();
+        received.iter_mut().for_each(|x| *x ^= 0x5a);
+        assert_eq!(received, encode(&data));
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn decode_pod() {
+        let x: u32 = decode_to_pod(&encode(&hex!("11223344"))).unwrap();
+        assert_eq!(x, u32::from_ne_bytes(hex!("11223344")));
+        let x: [u16; 4] = decode_to_pod(&encode(&hex!("0011223344556677"))).unwrap();
+        assert_eq!(bytemuck::bytes_of(&x), &hex!("0011223344556677"));
+
+        // Trailing zeros in the message are indistinguishable from padding.
+        let x: [u8; 5] = decode_to_pod(&encode(&hex!("11"))).unwrap();
+        assert_eq!(x, hex!("1100000000"));
+
+        assert_eq!(
+            decode_to_pod::<[u8; 2]>(&encode(&hex!("112233"))),
+            Err(DecodeError::BufferOverflow { needed: 7 })
+        );
+        assert_eq!(
+            decode_to_pod::<[u8; 8]>(&encode(&hex!("112233"))),
+            Err(DecodeError::LengthMismatch)
+        );
+        assert_eq!(
+            decode_to_pod::<u32>(&hex!("1122")),
+            Err(DecodeError::Truncated {
+                needed: 5,
+                found: 1
+            })
+        );
+    }
+
+    #[test]
+    fn encode_slice_at() {
+        let mut buf = [0xaa; 8];
+        let got = encode_to_slice_at(&hex!("0102"), &mut buf, 2).unwrap();
+        assert_eq!(got, &hex!("aaaa01027c"));
+        got[..2].copy_from_slice(&hex!("1234"));
+        assert_eq!(buf, hex!("123401027caaaaaa"));
+
+        assert_eq!(encode_to_slice_at(&[], &mut buf, 8).map(|x| x.len()), Ok(8));
+        assert_eq!(
+            encode_to_slice_at(&[], &mut buf, 9),
+            Err(BufferOverflowError)
+        );
+        assert_eq!(
+            encode_to_slice_at(&hex!("0102"), &mut buf, 6),
+            Err(BufferOverflowError)
+        );
+    }
+
+    #[test]
+    fn short_zero_group() {
+        assert_eq!(encode(&[]), &[]);
+        assert_eq!(decode(&[]).unwrap(), &[]);
+
+        for len in 1..=7 {
+            let zeros = vec![0; len];
+            assert_eq!(encode(&zeros), &[0x7f]);
+
+            for pos in 0..len {
+                let mut data = zeros.clone();
+                data[pos] = 0x44;
+                let header = 0x7f & !(1 << pos);
+                assert_eq!(encode(&data), &[0x44, header], "len {} pos {}", len, pos);
+
+                let mut group = [0; 7];
+                group[pos] = 0x44;
+                assert_eq!(decode(&[0x44, header]).unwrap(), &group);
+            }
+        }
+    }
+
+    #[cfg(feature = "asynchronous-codec")]
+    #[test]
+    fn codec() {
+        use asynchronous_codec::{BytesMut, Decoder, Encoder};
+
+        let mut codec = RzcobsCodec::new(16);
+        let mut buf = BytesMut::new();
+        codec.encode(&hex!("0102"), &mut buf).unwrap();
+        codec.encode(&hex!("11223344556677"), &mut buf).unwrap();
+        assert_eq!(&buf[..], &hex!("01027c00 112233445566778000"));
+
+        let mut rx = BytesMut::new();
+        rx.extend_from_slice(&buf[..6]);
+        assert_eq!(
+            codec.decode(&mut rx).unwrap().unwrap(),
+            hex!("01020000000000")
+        );
+        assert_eq!(codec.decode(&mut rx).unwrap(), None);
+        rx.extend_from_slice(&buf[6..]);
+        assert_eq!(
+            codec.decode(&mut rx).unwrap().unwrap(),
+            hex!("1122334455667700")
+        );
+        assert_eq!(codec.decode(&mut rx).unwrap(), None);
+
+        rx.extend_from_slice(&hex!("7c00"));
+        assert!(matches!(
+            codec.decode(&mut rx),
+            Err(CodecError::Decode(DecodeError::Truncated { .. }))
+        ));
+        assert!(rx.is_empty());
+
+        // Too long, without and then with a separator.
+        rx.extend_from_slice(&[0x11; 17]);
+        assert!(matches!(
+            codec.decode(&mut rx),
+            Err(CodecError::Decode(DecodeError::FrameTooLong))
+        ));
+        rx.extend_from_slice(&[0x11; 100]);
+        assert_eq!(codec.decode(&mut rx).unwrap(), None);
+        rx.extend_from_slice(&hex!("11 00 01027c 00"));
+        assert_eq!(
+            codec.decode(&mut rx).unwrap().unwrap(),
+            hex!("01020000000000")
+        );
+        rx.extend_from_slice(&[0x11; 17]);
+        rx.extend_from_slice(&hex!("00"));
+        assert!(matches!(
+            codec.decode(&mut rx),
+            Err(CodecError::Decode(DecodeError::FrameTooLong))
+        ));
+        assert!(rx.is_empty());
+    }
+
+    #[test]
+    fn resync() {
+        let stream = hex!("223344 8000 01027c00 7c00 00 1122");
+        let (msgs, consumed) = decode_resync(&stream);
+        assert_eq!(
+            msgs,
+            vec![
+                Ok(hex!("01020000000000").to_vec()),
+                Err(DecodeError::Truncated {
+                    needed: 2,
+                    found: 0
+                }),
+                Ok(vec![]),
+            ]
+        );
+        assert_eq!(consumed, stream.len() - 2);
+
+        assert_eq!(decode_resync(&hex!("01027c")), (vec![], 3));
+        assert_eq!(decode_resync(&hex!("01027c00")), (vec![], 4));
+    }
+
+    #[test]
+    fn const_encode_matches() {
+        const MSG: &[u8] = &hex!("00000000004400 000000000000ff 01");
+        const ENC: [u8; encoded_len(MSG)] = const_encode(MSG);
+        assert_eq!(&ENC[..], &encode(MSG)[..]);
+
+        for data in [&[0xff; 300][..], &[0; 300][..], &hex!("1122334455667788")] {
+            assert_eq!(encoded_len(data), encode(data).len());
+        }
+        let data = [0x11; 134];
+        assert_eq!(&const_encode::<135>(&data)[..], &encode(&data)[..]);
+    }
+
+    #[test]
+    fn chunked_decode() {
+        let data = sample_message_mixed();
+        let enc = encode(&data);
+        let dec = decode(&enc).unwrap();
+
+        let mut chunks = Vec::new();
+        decode_chunked::<16, DecodeError, _>(&enc, |chunk| {
+            chunks.push(chunk.to_vec());
+            Ok(())
+        })
+        .unwrap();
+        assert!(chunks[..chunks.len() - 1].iter().all(|c| c.len() == 16));
+        assert_eq!(chunks.concat(), dec);
+
+        let mut called = false;
+        let res = decode_chunked::<16, DecodeError, _>(&hex!("7f7c"), |_| {
+            called = true;
+            Ok(())
+        });
+        assert_eq!(
+            res,
+            Err(DecodeError::Truncated {
+                needed: 2,
+                found: 1
+            })
+        );
+        assert!(!called);
+    }
+
+    #[test]
+    fn max_encoded() {
+        let mut buf = [0; 64];
+        let mut enc = Encoder::with_max_encoded(SliceWriter::new(&mut buf), 10);
+        for &b in &hex!("0102030405060708090a") {
+            enc.write(b).unwrap();
+        }
+        assert_eq!(enc.write(0x0b), Err(LimitError::LimitExceeded));
+        assert_eq!(enc.end(), Err(LimitError::LimitExceeded));
+
+        // The limit is per message.
+        enc.abort();
+        enc.writer().write(0).unwrap();
+        for &b in &hex!("0102030405060708") {
+            enc.write(b).unwrap();
+        }
+        enc.end().unwrap();
+        let len = enc.writer().writer().written();
+        assert_eq!(&buf[len - 9..len], &hex!("010203040506070881"));
+
+        let data = [0xff; 300];
+        let mut buf = [0; 400];
+        let mut enc =
+            Encoder::with_max_encoded(SliceWriter::new(&mut buf), max_encoded_len(data.len()));
+        for &b in &data {
+            enc.write(b).unwrap();
+        }
+        enc.end().unwrap();
+
+        assert_eq!(decoded_len(&[0x7f; 10]), Ok(max_decoded_len(10)));
+    }
+
+    #[cfg(feature = "test-util")]
+    #[test]
+    fn compare() {
+        let s = debug_compare(&hex!("112233445566778881"), &hex!("112233445566779981"));
+        assert_eq!(
+            s,
+            "first difference at byte 7\n\
+             \x20      expected                 | got\n\
+             > 0000 11 22 33 44 55 66 77 88 | 11 22 33 44 55 66 77 99\n\
+             \x20 0008 81                      | 81\n\
+             expected decodes to: 112233445566778800\n\
+             got decodes to: 112233445566779900\n"
+        );
+
+        let s = debug_compare(&hex!("017e"), &hex!("01"));
+        assert!(s.starts_with("first difference at byte 1\n"));
+        assert!(s.ends_with("got decodes to: malformed\n"));
+    }
+
+    #[test]
+    fn flush() {
+        let data = hex!("11223300556677 889900");
+        let mut buf = [0; 16];
+        let mut enc = Encoder::new(SliceWriter::new(&mut buf));
+        for &b in &data[..7] {
+            enc.write(b).unwrap();
+        }
+        assert_eq!(enc.writer().written(), 6);
+        assert_eq!(enc.bytes_until_flush(), 0);
+        enc.flush().unwrap();
+        assert_eq!(enc.writer().written(), 7);
+        assert_eq!(enc.bytes_until_flush(), 134);
+        enc.flush().unwrap();
+        assert_eq!(enc.writer().written(), 7);
+        for &b in &data[7..9] {
+            enc.write(b).unwrap();
+            enc.flush().unwrap();
+        }
+        assert_eq!(enc.bytes_until_flush(), 132);
+        enc.write(data[9]).unwrap();
+        assert_eq!(enc.bytes_until_flush(), 4);
+        enc.end().unwrap();
+        let len = enc.writer().written();
+        assert_eq!(&buf[..len], &encode(&data)[..]);
+    }
+
+    #[test]
+    fn write_bytes_and_fmt() {
+        use core::fmt::Write as _;
+
+        let mut buf = [0; 32];
+        let mut enc = Encoder::new(SliceWriter::new(&mut buf));
+        enc.write_bytes(b"temp=").unwrap();
+        write!(enc, "{}.{}", -12, 5).unwrap();
+        enc.end().unwrap();
+        let len = enc.writer().written();
+        assert_eq!(&buf[..len], &encode(b"temp=-12.5")[..]);
+
+        let mut buf = [0; 4];
+        let mut enc = Encoder::new(SliceWriter::new(&mut buf));
+        assert!(write!(enc, "{}", 123456).is_err());
+    }
+
+    #[test]
+    fn truncated() {
+        // Zero group, literal run and max run headers, each missing one byte.
+        assert_eq!(decode(&hex!("01027c")[1..]), Err(MalformedError));
+        let tests: &[(&[u8], usize)] = &[
+            (&hex!("01027c"), 2),
+            (&hex!("112233445566778881"), 8),
+            (&encode(&[0x11; 134]), 134),
+            (&hex!("01027c 7f"), 2),
+        ];
+        for &(enc, needed) in tests {
+            for cut in 1..=needed {
+                let truncated = &enc[cut..];
+                let err = DecodeError::Truncated {
+                    needed,
+                    found: needed - cut,
+                };
+                assert_eq!(decoded_len(truncated), Err(err));
+                assert_eq!(decode_to_slice(truncated, &mut [0; 200]), Err(err));
+            }
+        }
+        assert_eq!(
+            decode_to_slice(&hex!("ff"), &mut [0; 200]),
+            Err(DecodeError::Truncated {
+                needed: 134,
+                found: 0
+            })
+        );
+        assert_eq!(
+            decoded_len(&hex!("017e00")),
+            Err(DecodeError::MalformedError)
+        );
+    }
+
+    #[test]
+    fn batch() {
+        let (stream, offsets) = encode_batch(&[&hex!("0102"), &[], &hex!("11223344556677")]);
+        assert_eq!(stream, hex!("01027c 00 00 1122334455667780"));
+        assert_eq!(offsets, vec![0, 4, 5]);
+
+        assert_eq!(encode_batch(&[]), (vec![], vec![]));
+
+        let mut buf = hex!("aa").to_vec();
+        encode_into(&hex!("0102"), &mut buf);
+        assert_eq!(buf, hex!("aa 01027c"));
+    }
+
+    #[test]
+    fn io_write() {
+        let data = hex!("1122334400556677");
+        let mut out = Vec::new();
+        let mut enc = Encoder::new(IoWrite(std::io::Cursor::new(&mut out)));
+        enc.write_bytes(&data).unwrap();
+        enc.end().unwrap();
+        assert_eq!(out, encode(&data));
+    }
+
+    #[test]
+    fn cow() {
+        assert!(matches!(decode_cow(&[]), Ok(Cow::Borrowed(&[]))));
+        match decode_cow(&hex!("01027c")) {
+            Ok(Cow::Owned(v)) => assert_eq!(v, hex!("01020000000000")),
+            x => panic!("unexpected {:?}", x),
+        }
+        assert_eq!(decode_cow(&hex!("7f")).unwrap(), &[0; 7][..]);
+        assert_eq!(
+            decode_cow(&hex!("017e00")),
+            Err(DecodeError::MalformedError)
+        );
+    }
+
+    #[cfg(feature = "strict")]
+    #[test]
+    fn strict() {
+        let data = hex!("11002233445566 80");
+        assert_eq!(decoded_len(&data), Err(DecodeError::NonCanonical));
+        assert_eq!(
+            decode_to_slice(&data, &mut [0; 16]),
+            Err(DecodeError::NonCanonical)
+        );
+        assert_eq!(decode(&data), Err(MalformedError));
+
+        let data = hex!("1100223344556677 01");
+        assert_eq!(decode(&data), Err(MalformedError));
+    }
+
+    #[test]
+    fn from_reader() {
+        let data = sample_message();
+
+        let mut out = Vec::new();
+        encode_from_reader(&data[..], &mut out).unwrap();
+        let mut expected = encode(&data);
+        expected.push(0);
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn inspect_headers() {
+        let mut data = vec![0x11; 134];
+        data.extend(hex!("1122334455667788 00 0102"));
+        let headers = inspect(&encode(&data)).unwrap();
+        assert_eq!(
+            headers,
+            vec![
+                HeaderInfo {
+                    pos: 134,
+                    header: 0xff,
+                    kind: HeaderKind::MaxRun,
+                    consumed: 134,
+                    produced: 134,
+                },
+                HeaderInfo {
+                    pos: 143,
+                    header: 0x81,
+                    kind: HeaderKind::Run,
+                    consumed: 8,
+                    produced: 9,
+                },
+                HeaderInfo {
+                    pos: 146,
+                    header: 0x7c,
+                    kind: HeaderKind::ZeroGroup,
+                    consumed: 2,
+                    produced: 7,
+                },
+            ]
+        );
+        assert_eq!(inspect(&[]), Ok(vec![]));
+        assert_eq!(inspect(&hex!("017e00")), Err(DecodeError::MalformedError));
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn decode_heapless() {
+        let data = hex!("1122334455667788 00 0102");
+        let enc = encode(&data);
+        let dec = decode_to_heapless::<16>(&enc).unwrap();
+        assert_eq!(&dec[..], &decode(&enc).unwrap()[..]);
+        assert_eq!(dec.len(), 16);
+        assert_eq!(
+            decode_to_heapless::<15>(&enc),
+            Err(DecodeError::BufferOverflow { needed: 16 })
+        );
+    }
+
+    #[test]
+    fn end_at() {
+        let mut buf = [0; 32];
+        let mut enc = Encoder::new(SliceWriter::new(&mut buf));
+        enc.write_bytes(&hex!("1122334455667788 00 0102")).unwrap();
+        assert_eq!(enc.end_at(), Ok(12));
+        enc.writer().write(0).unwrap();
+        assert_eq!(enc.end_at(), Ok(0));
+        enc.write_bytes(&hex!("0102")).unwrap();
+        assert_eq!(enc.end_at(), Ok(3));
+        let len = enc.writer().written();
+        assert_eq!(&buf[..len], hex!("1122334455667788 81 01027c 00 01027c"));
+    }
+
+    #[test]
+    fn variant() {
+        let data = hex!("1122 00 33");
+        assert_eq!(
+            decode_variant(&encode(&data), CobsVariant::Rzcobs),
+            decode(&encode(&data)).map_err(|_| DecodeError::MalformedError)
+        );
+
+        let rcobs = hex!("1122 03 33 02");
+        assert_eq!(
+            decode_variant(&rcobs, CobsVariant::Rcobs),
+            Ok(data.to_vec())
+        );
+        assert_eq!(decode_variant(&hex!("01"), CobsVariant::Rcobs), Ok(vec![]));
+        assert_eq!(
+            decode_variant(&hex!("0101"), CobsVariant::Rcobs),
+            Ok(vec![0])
+        );
+
+        let mut rcobs = vec![0x11; 254];
+        rcobs.extend(hex!("ff 01"));
+        assert_eq!(
+            decode_variant(&rcobs, CobsVariant::Rcobs),
+            Ok(vec![0x11; 254])
+        );
+
+        assert_eq!(
+            decode_variant(&hex!("1104"), CobsVariant::Rcobs),
+            Err(DecodeError::Truncated {
+                needed: 3,
+                found: 1
+            })
+        );
+        assert_eq!(
+            decode_variant(&hex!("1100"), CobsVariant::Rcobs),
+            Err(DecodeError::MalformedError)
+        );
+    }
+
+    #[test]
+    fn rollback() {
+        let mut out = hex!("aa").to_vec();
+        let mut enc = Encoder::new(&mut out);
+        enc.write_bytes(&hex!("1122334455667788 00 0102")).unwrap();
+        enc.rollback();
+        enc.write_bytes(&hex!("0102")).unwrap();
+        enc.end().unwrap();
+        assert_eq!(out, hex!("aa 01027c"));
+
+        let mut buf = [0; 16];
+        let mut enc = Encoder::new(SliceWriter::new(&mut buf));
+        enc.write_bytes(&hex!("0102")).unwrap();
+        enc.end().unwrap();
+        enc.writer().write(0).unwrap();
+        enc.write_bytes(&hex!("11223344")).unwrap();
+        enc.rollback();
+        assert_eq!(enc.writer().written(), 4);
+        enc.write(0x33).unwrap();
+        enc.end().unwrap();
+        let len = enc.writer().written();
+        assert_eq!(&buf[..len], hex!("01027c 00 337e"));
+    }
+
+    #[test]
+    fn padding() {
+        let data = hex!("11223344556677 8899");
+        for (len, padding) in [
+            (1, 6),
+            (2, 5),
+            (3, 4),
+            (4, 3),
+            (5, 2),
+            (6, 1),
+            (7, 1),
+            (9, 1),
+        ] {
+            let (dec, got) = decode_ex(&encode(&data[..len])).unwrap();
+            assert_eq!(got, padding, "message length {}", len);
+            assert_eq!(&dec[..dec.len() - got], &data[..len]);
+        }
+
+        let (dec, padding) = decode_ex(&encode(&hex!("110000"))).unwrap();
+        assert_eq!((&dec[..], padding), (&hex!("11000000000000")[..], 6));
+        assert_eq!(decode_ex(&[]), Ok((vec![], 0)));
+    }
+
+    #[test]
+    fn framer() {
+        // Accepts `self.1` more bytes.
+        struct Channel(Vec<u8>, usize);
+        impl Write for Channel {
+            type Error = ();
+            fn write(&mut self, byte: u8) -> Result<(), ()> {
+                if self.1 == 0 {
+                    return Err(());
+                }
+                self.1 -= 1;
+                self.0.push(byte);
+                Ok(())
+            }
+        }
+
+        let mut f = Framer::new(Channel(Vec::new(), 6));
+        f.log_frame(&hex!("0102")).unwrap();
+        assert_eq!(f.log_frame(&hex!("11223344")), Err(()));
+        f.writer().1 = 100;
+        f.log_frame(&hex!("0102")).unwrap();
+        assert_eq!(f.writer().0, hex!("01027c 00 1122 00 01027c 00"));
+    }
+
+    #[test]
+    fn random_roundtrip() {
+        let mut rand = rng(0x2545_f491_4f6c_dd1d);
+
+        let mut buf = vec![0; 1024];
+        for i in 0..4000 {
+            // Lengths around the 134 byte run limit, with zero density from 0 to 100%.
+            let len = match i % 3 {
+                0 => rand() as usize % 16,
+                1 => 128 + rand() as usize % 16,
+                _ => rand() as usize % 800,
+            };
+            let density = rand() % 11;
+            let data: Vec<u8> = (0..len)
+                .map(|_| match rand() % 10 < density {
+                    true => 0,
+                    false => (rand() % 255) as u8 + 1,
+                })
+                .collect();
+
+            let enc = encode(&data);
+            assert!(!enc.contains(&0));
+            let dec = decode(&enc).unwrap();
+            assert_eq!(&dec[..len], &data[..], "{:02x?}", data);
+            assert!(dec.len() - len <= 6);
+            assert!(dec[len..].iter().all(|&b| b == 0));
+
+            assert_eq!(decode_to_slice(&enc, &mut buf).unwrap(), &dec[..]);
+        }
+    }
+
+    #[test]
+    fn sparse_zeros() {
+        // Telemetry-like: long runs of non-zero samples, with a zero now and then.
+        let data: Vec<u8> = (0..100_000u32)
+            .map(|i| match i % 509 {
+                0 => 0,
+                _ => (i % 251) as u8 + 1,
+            })
+            .collect();
+        let enc = encode(&data);
+        let dec = decode(&enc).unwrap();
+        assert_eq!(&dec[..data.len()], &data[..]);
+
+        let mut buf = vec![0; dec.len()];
+        assert_eq!(decode_to_slice(&enc, &mut buf).unwrap(), &dec[..]);
+    }
+
+    #[test]
+    fn forward_with() {
+        let data = sample_message();
+        let enc = encode(&data);
+
+        let mut out = Vec::new();
+        decode_forward_with(&enc, |b| out.push(b)).unwrap();
+        assert_eq!(out, decode(&enc).unwrap());
+
+        let mut called = false;
+        assert_eq!(
+            decode_forward_with(&hex!("1122 00 7f"), |_| called = true),
+            Err(DecodeError::MalformedError)
+        );
+        assert!(!called);
+    }
+
+    #[test]
+    fn reserve() {
+        let data = [0x11; 1000];
+        let mut out = Vec::new();
+        encode_into(&data, &mut out);
+        assert!(out.capacity() >= max_encoded_len(data.len()));
+
+        let mut out = Vec::new();
+        let mut enc = Encoder::new(MapWriter::new(&mut out, |b| b));
+        enc.reserve(100);
+        assert!(enc.writer().writer().capacity() >= 101);
+    }
+
+    #[test]
+    fn max_run_boundary() {
+        let mut buf = [0; 200];
+        for found in [0, 1, 133] {
+            let mut data = vec![0x11; found];
+            data.push(0xff);
+            let err = DecodeError::Truncated { needed: 134, found };
+            assert_eq!(decoded_len(&data), Err(err));
+            assert_eq!(decode_to_slice(&data, &mut buf), Err(err));
+            assert_eq!(decode(&data), Err(MalformedError));
+        }
+
+        let mut data = vec![0x11; 134];
+        data.push(0xff);
+        assert_eq!(decode(&data).unwrap(), vec![0x11; 134]);
+        assert_eq!(decode_to_slice(&data, &mut buf).unwrap(), &[0x11; 134][..]);
+    }
+
+    #[test]
+    fn framed() {
+        assert_eq!(encode_framed(&hex!("0102")), hex!("01027c 00"));
+        assert_eq!(encode_framed(&[]), hex!("00"));
+    }
+
+    #[test]
+    fn capture() {
+        let data = hex!("00 01027c 00 017e00 01027c 00 00 11 7e 00 017f 00 7f");
+        assert_eq!(
+            split_frames(&data, SplitMode::SkipEmpty),
+            vec![
+                &hex!("01027c")[..],
+                &hex!("017e"),
+                &hex!("01027c"),
+                &hex!("117e"),
+                &hex!("017f"),
+                &hex!("7f"),
+            ]
+        );
+
+        let data = hex!("01027c 00 11223344 00 7f 00 ff");
+        assert_eq!(
+            decode_capture(&data),
+            vec![
+                Ok(hex!("01020000000000").to_vec()),
+                Err((
+                    4,
+                    DecodeError::Truncated {
+                        needed: 5,
+                        found: 3
+                    }
+                )),
+                Ok(vec![0; 7]),
+                Err((
+                    11,
+                    DecodeError::Truncated {
+                        needed: 134,
+                        found: 0
+                    }
+                )),
+            ]
+        );
+    }
+
+    #[test]
+    fn nonzero() {
+        let data: Vec<u8> = (0..600).map(|i| (i % 255) as u8 + 1).collect();
+        for len in 0..data.len() {
+            assert_eq!(
+                encode_nonzero(&data[..len]),
+                encode(&data[..len]),
+                "{}",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn tagged_frame() {
+        let mut out = Vec::new();
+        let mut enc = Encoder::new(&mut out);
+        enc.begin_frame(0x42).unwrap();
+        enc.write_bytes(&hex!("0102")).unwrap();
+        enc.end_frame().unwrap();
+        enc.begin_frame(0).unwrap();
+        enc.end_frame().unwrap();
+        assert_eq!(out, hex!("420102 78 7f"));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "frame already started")]
+    fn tagged_frame_twice() {
+        let mut enc = Encoder::new(Vec::new());
+        enc.begin_frame(0).unwrap();
+        enc.begin_frame(0).unwrap();
+    }
+
+    #[test]
+    fn exact_into() {
+        let enc = hex!("01027c");
+        for len in 2..=7 {
+            let mut out = [0xaa; 7];
+            decode_exact_into(&enc, &mut out[..len]).unwrap();
+            assert_eq!(&out[..len], &hex!("01020000000000")[..len]);
+        }
+        assert_eq!(
+            decode_exact_into(&enc, &mut [0; 1]),
+            Err(DecodeError::BufferOverflow { needed: 7 })
+        );
+        assert_eq!(
+            decode_exact_into(&enc, &mut [0; 8]),
+            Err(DecodeError::LengthMismatch)
+        );
+    }
+
+    #[test]
+    fn map() {
+        let frame = encode(&hex!("01 1122334455667788 00 02"));
+        let mut out = Vec::new();
+        map_frame(&frame, &mut out, |msg| msg[0] = 0x42).unwrap();
+        assert_eq!(out, encode(&hex!("42 1122334455667788 00 02")));
+
+        let mut again = Vec::new();
+        map_frame(&out, &mut again, |_| {}).unwrap();
+        assert_eq!(again, out);
+
+        assert_eq!(
+            map_frame(&hex!("017e00"), &mut out, |_| {}),
+            Err(DecodeError::MalformedError)
+        );
+        assert_eq!(again, out);
+    }
+
+    #[test]
+    fn split_modes() {
+        fn check(data: &[u8], skip: &[&[u8]], yield_: &[&[u8]]) {
+            assert_eq!(
+                split_frames(data, SplitMode::SkipEmpty),
+                skip,
+                "{:02x?}",
+                data
+            );
+            assert_eq!(
+                split_frames(data, SplitMode::YieldEmpty),
+                yield_,
+                "{:02x?}",
+                data
+            );
+        }
+
+        check(&[], &[], &[]);
+        check(&hex!("00"), &[], &[&[]]);
+        check(&hex!("0000"), &[], &[&[], &[]]);
+        check(&hex!("7f"), &[&hex!("7f")], &[&hex!("7f")]);
+        check(&hex!("7f00"), &[&hex!("7f")], &[&hex!("7f")]);
+        check(&hex!("007f00"), &[&hex!("7f")], &[&[], &hex!("7f")]);
+        check(&hex!("7f0000"), &[&hex!("7f")], &[&hex!("7f"), &[]]);
+        check(
+            &hex!("7f00007f"),
+            &[&hex!("7f"), &hex!("7f")],
+            &[&hex!("7f"), &[], &hex!("7f")],
+        );
+        assert_eq!(SplitMode::default(), SplitMode::SkipEmpty);
+    }
+
+    #[test]
+    fn pack_frames() {
+        let msg = hex!("1122334455667788 00 0102");
+        // Each message takes 12 bytes and its separator.
+        let needed = max_encoded_len(msg.len()) + 1;
+        let mut buf = [0; 39];
+        let mut enc = Encoder::new(SliceWriter::new(&mut buf));
+        let mut count = 0;
+        while enc.writer().remaining() >= needed {
+            enc.write_bytes(&msg).unwrap();
+            enc.end().unwrap();
+            enc.writer().write(0).unwrap();
+            count += 1;
+        }
+        assert_eq!(count, 3);
+        assert_eq!(enc.writer().remaining(), 0);
+    }
+
+    #[test]
+    fn ratio_limited() {
+        let data = [0x7f; 10];
+        assert_eq!(decode_ratio_limited(&data, 7), Ok(vec![0; 70]));
+        assert_eq!(
+            decode_ratio_limited(&data, 6),
+            Err(DecodeError::RatioExceeded)
+        );
+
+        let data = encode(&[0x11; 134]);
+        assert_eq!(decode_ratio_limited(&data, 1), Ok(vec![0x11; 134]));
+        assert_eq!(
+            decode_ratio_limited(&data, 0),
+            Err(DecodeError::RatioExceeded)
+        );
+        assert_eq!(decode_ratio_limited(&[], 0), Ok(vec![]));
+        assert_eq!(
+            decode_ratio_limited(&hex!("017e00"), 7),
+            Err(DecodeError::MalformedError)
+        );
+    }
+
+    #[test]
+    fn looks_like() {
+        assert!(looks_like_rzcobs(&encode(&hex!(
+            "1122334455667788 00 0102"
+        ))));
+        assert!(looks_like_rzcobs(&[]));
+        assert!(!looks_like_rzcobs(&hex!("01027c 00")));
+        assert!(!looks_like_rzcobs(&hex!("1100227c")));
+        assert!(!looks_like_rzcobs(&hex!("11223344")));
+        assert!(!looks_like_rzcobs(&hex!("ff")));
+    }
+
+    #[test]
+    fn inverted() {
+        let data = hex!("1122334455667788 00 0102");
+        let enc = encode_framed_inverted(&data);
+        let mut expected: Vec<u8> = encode(&data).iter().map(|&b| !b).collect();
+        expected.push(0xff);
+        assert_eq!(enc, expected);
+        assert!(!enc[..enc.len() - 1].contains(&0xff));
+
+        let dec = decode(&encode(&data)).unwrap();
+        assert_eq!(decode_framed_inverted(&enc), Ok(dec.clone()));
+        assert_eq!(decode_framed_inverted(&enc[..enc.len() - 1]), Ok(dec));
+        assert_eq!(
+            decode_framed_inverted(&hex!("fe81ffff")),
+            Err(DecodeError::MalformedError)
+        );
+    }
+
+    #[test]
+    fn stats_writer() {
+        let mut f = Framer::new(StatsWriter::new(Vec::new()));
+        f.log_frame(&hex!("0102")).unwrap();
+        f.log_frame(&[]).unwrap();
+        f.log_frame(&hex!("1122334455667788 00 0102")).unwrap();
+        assert_eq!(
+            f.writer().stats(),
+            WriterStats {
+                bytes: 4 + 1 + 13,
+                frames: 3
+            }
+        );
+        assert_eq!(f.writer().writer().len(), 18);
+    }
+
+    #[test]
+    fn frames_iter() {
+        let data = hex!("00 01027c 00 00 11 7e 00 7f");
+        let mut buf = [0; 8];
+        let mut it = frames(&data);
+        assert_eq!(it.next(), Some(&hex!("01027c")[..]));
+        let frame = it.next().unwrap();
+        assert_eq!(
+            decode_to_slice(frame, &mut buf).unwrap(),
+            &hex!("11000000000000")
+        );
+        assert_eq!(it.next(), Some(&hex!("7f")[..]));
+        assert_eq!(it.next(), None);
+
+        let got: Vec<_> = Frames::new(&data, SplitMode::YieldEmpty).collect();
+        assert_eq!(got, split_frames(&data, SplitMode::YieldEmpty));
+    }
+
+    #[test]
+    fn sizes() {
+        let mut data = encode_framed(&hex!("1122334455667788 00 0102"));
+        data.extend(encode_framed(&[0x11; 200]));
+        data.extend(hex!("11223344 00"));
+        assert_eq!(
+            frame_sizes(&data),
+            vec![
+                (12, Ok(11)),
+                (202, Ok(200)),
+                (
+                    4,
+                    Err(DecodeError::Truncated {
+                        needed: 5,
+                        found: 3
+                    })
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn to_writer() {
+        let data = sample_message();
+        let enc = encode(&data);
+
+        let mut out = Vec::new();
+        decode_to_writer(&enc, &mut out).unwrap();
+        assert_eq!(out, decode(&enc).unwrap());
+
+        let mut buf = [0; 100];
+        assert_eq!(
+            decode_to_writer(&enc, SliceWriter::new(&mut buf)),
+            Err(DecodeWriteError::Writer(BufferOverflowError))
+        );
+        assert_eq!(
+            decode_to_writer(&hex!("017e00"), &mut out),
+            Err(DecodeWriteError::Decode(DecodeError::MalformedError))
+        );
+    }
+
+    #[test]
+    fn reverse_chunks() {
+        let data = sample_message_mixed();
+        let enc = encode(&data);
+        let dec = decode(&enc).unwrap();
+
+        for size in [1, 2, 7, 50, enc.len()] {
+            let mut buf = [0; 300];
+            let mut d = ReverseChunkDecoder::new(&mut buf);
+            for chunk in enc.rchunks(size) {
+                d.feed_reverse(chunk).unwrap();
+            }
+            assert_eq!(d.finish().unwrap(), &dec[..], "chunk size {}", size);
+        }
+
+        let mut buf = [0; 100];
+        let mut d = ReverseChunkDecoder::new(&mut buf);
+        d.feed_reverse(&enc).unwrap();
+        assert_eq!(
+            d.finish(),
+            Err(DecodeError::BufferOverflow { needed: dec.len() })
+        );
+
+        for (enc, needed, found) in [
+            (&hex!("223344")[..], 5, 2),
+            (&hex!("11223344556677 81"), 8, 7),
+            (&hex!("1122 ff"), 134, 2),
+        ] {
+            let mut d = ReverseChunkDecoder::new(&mut buf);
+            d.feed_reverse(enc).unwrap();
+            let err = Err(DecodeError::Truncated { needed, found });
+            assert_eq!(d.finish(), err);
+            assert_eq!(decode_to_slice(enc, &mut [0; 200]), err);
+        }
+
+        let mut d = ReverseChunkDecoder::new(&mut buf);
+        assert_eq!(
+            d.feed_reverse(&hex!("017e00")),
+            Err(DecodeError::MalformedError)
+        );
+    }
+
+    #[test]
+    fn accumulator() {
+        let mut acc = FrameAccumulator::new(16);
+        let mut got = Vec::new();
+        let mut stream = encode_framed(&hex!("0102"));
+        stream.extend([0x11; 10_000]);
+        stream.extend(hex!("00 00 7f 00"));
+        for &b in &stream {
+            got.extend(acc.push(b));
+        }
+        assert_eq!(
+            got,
+            vec![
+                Ok(hex!("01020000000000").to_vec()),
+                Err(DecodeError::FrameTooLong),
+                Ok(vec![0; 7]),
+            ]
+        );
+        assert!(acc.buf.capacity() <= 16);
+    }
+
+    #[test]
+    fn len_prefixed() {
+        let mut stream = encode_framed(&hex!("0102"));
+        stream.extend(encode_framed(&[0x11; 200]));
+        stream.push(0);
+
+        let mut expected = hex!("03 01027c ca01").to_vec();
+        expected.extend(&stream[4..][..202]);
+        assert_eq!(reframe_to_len_prefixed(&stream), Ok(expected));
+
+        assert_eq!(
+            reframe_to_len_prefixed(&hex!("01027c 00 017e 7f00 0102")),
+            Err(DecodeError::Truncated {
+                needed: 6,
+                found: 1
+            })
+        );
+    }
+
+    #[test]
+    fn until_separator() {
+        assert_eq!(
+            decode_until_separator(&hex!("01027c 00 aabbcc 00 00")),
+            Ok((hex!("01020000000000").to_vec(), 4))
+        );
+        assert_eq!(
+            decode_until_separator(&hex!("01027c")),
+            Ok((hex!("01020000000000").to_vec(), 3))
+        );
+        assert_eq!(decode_until_separator(&hex!("00 7f")), Ok((vec![], 1)));
+        assert_eq!(
+            decode_until_separator(&hex!("027c 00")),
+            Err(DecodeError::Truncated {
+                needed: 2,
+                found: 1
+            })
+        );
+    }
 
-            let got = decode(enc).unwrap();
-            assert_eq!(&got[..dec.len()], *dec);
-            assert!(&got[dec.len()..].iter().all(|&x| x == 0));
+    #[test]
+    fn precoded_suffix() {
+        const TAIL: [u8; encoded_len(b"tail")] = const_encode(b"tail");
+        let head = hex!("1122330044556677");
 
-            let got_slice = decode_to_slice(enc, &mut scratch).unwrap();
-            assert_eq!(&got_slice[..dec.len()], *dec);
-            assert!(&got_slice[dec.len()..].iter().all(|&x| x == 0));
+        let mut out = Vec::new();
+        let mut enc = Encoder::new(&mut out);
+        enc.write_bytes(&head[..7]).unwrap();
+        enc.write_precoded_suffix(&TAIL).unwrap();
+        let mut data = head[..7].to_vec();
+        data.extend(b"tail");
+        assert_eq!(out, encode(&data));
+
+        let mut out = Vec::new();
+        let mut enc = Encoder::new(&mut out);
+        for _ in 0..2 {
+            enc.write_precoded_suffix(&TAIL).unwrap();
+        }
+        assert_eq!(out, [TAIL, TAIL].concat());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "not at a group boundary")]
+    fn precoded_suffix_mid_group() {
+        let mut enc = Encoder::new(Vec::new());
+        enc.write(0x11).unwrap();
+        enc.write_precoded_suffix(&hex!("01027c")).unwrap();
+    }
+
+    #[test]
+    fn prepare() {
+        let mut rand = rng(0x9e37_79b9_7f4a_7c15);
+
+        // Random data, mostly malformed, and valid messages with random corruption.
+        for i in 0..2000 {
+            let len = rand() as usize % 40;
+            let mut data: Vec<u8> = (0..len).map(|_| rand() as u8).collect();
+            if i % 2 == 0 {
+                data = encode(&data);
+                if let Some(b) = data.get_mut(rand() as usize % (len + 1)) {
+                    *b = rand() as u8;
+                }
+            }
+
+            let mut buf = [0; 300];
+            match prepare_decode(&data) {
+                Ok(len) => {
+                    let dec = decode_to_slice(&data, &mut buf[..len]).unwrap();
+                    assert_eq!(dec.len(), len);
+                }
+                Err(e) => assert_eq!(decode_to_slice(&data, &mut buf), Err(e)),
+            }
+        }
+    }
+
+    #[test]
+    fn hash() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::Hasher;
+
+        let data = sample_message();
+        let enc = encode(&data);
+
+        let mut expected = DefaultHasher::new();
+        for b in decode(&enc).unwrap() {
+            expected.write_u8(b);
+        }
+        let mut got = DefaultHasher::new();
+        decode_hash(&enc, &mut got).unwrap();
+        assert_eq!(got.finish(), expected.finish());
+
+        assert_eq!(
+            decode_hash(&hex!("017e00"), &mut got),
+            Err(DecodeError::MalformedError)
+        );
+    }
+
+    #[test]
+    fn hex_string() {
+        assert_eq!(encode_hex(&hex!("0102")), "01027c00");
+        assert_eq!(encode_hex(&[]), "00");
+    }
+
+    #[test]
+    fn encode_reversed() {
+        let data = hex!("01 00 02 03 00 00 04 05 06 07 08 09 0a 0b");
+        assert_eq!(encode_rev(data.iter().rev().copied()), encode(&data));
+        assert_eq!(encode_rev(core::iter::empty()), encode(&[]));
+    }
+
+    #[test]
+    fn split_slices() {
+        let data = hex!("01 02 00 03 04 05 06 07 08 09 0a");
+        let enc = encode(&data);
+
+        for split in 0..=data.len() {
+            let mut first = [0xaa; 16];
+            let mut second = [0xaa; 16];
+            let (a, b) = decode_to_split_slices(&enc, &mut first[..split], &mut second).unwrap();
+            assert_eq!((a, b), (split, data.len() + 3 - split));
+            assert_eq!(first[..a], data[..a]);
+            assert_eq!(second[..b], [&data[a..], &[0; 3]].concat());
+        }
+
+        let mut first = [0; 4];
+        let mut second = [0; 4];
+        assert_eq!(
+            decode_to_split_slices(&enc, &mut first, &mut second),
+            Err(DecodeError::BufferOverflow { needed: 14 })
+        );
+    }
+
+    #[test]
+    fn header_ops() {
+        assert_eq!(decode_header(0x00), HeaderOp::EndOfFrame);
+        assert_eq!(decode_header(0x01), HeaderOp::ZeroGroup { zeros: 0x01 });
+        assert_eq!(decode_header(0x7f), HeaderOp::ZeroGroup { zeros: 0x7f });
+        assert_eq!(decode_header(0x80), HeaderOp::Run { literals: 7 });
+        assert_eq!(decode_header(0xfe), HeaderOp::Run { literals: 133 });
+        assert_eq!(decode_header(0xff), HeaderOp::MaxRun);
+
+        for header in 0..=255u8 {
+            let expected = match decode_header(header) {
+                HeaderOp::EndOfFrame => None,
+                HeaderOp::ZeroGroup { zeros } => Some((7 - zeros.count_ones() as usize, 7)),
+                HeaderOp::Run { literals } => Some((literals, literals + 1)),
+                HeaderOp::MaxRun => Some((134, 134)),
+            };
+            assert_eq!(header_len(header), expected, "header {:02x}", header);
+
+            // The op must match what the decoder does with a group of distinct literals.
+            if let Some((consumed, _)) = expected {
+                let mut data: Vec<u8> = (1..=consumed as u8).collect();
+                data.push(header);
+                let res = decode(&data).unwrap();
+                let mut literals = data[..consumed].iter().copied();
+                let expected: Vec<u8> = match decode_header(header) {
+                    HeaderOp::ZeroGroup { zeros } => (0..7)
+                        .map(|k| match zeros & (1 << k) {
+                            0 => literals.next().unwrap(),
+                            _ => 0,
+                        })
+                        .collect(),
+                    HeaderOp::Run { .. } => literals.chain([0]).collect(),
+                    _ => literals.collect(),
+                };
+                assert_eq!(res, expected, "header {:02x}", header);
+            }
+        }
+    }
+
+    #[test]
+    fn length_framed() {
+        let mut out = Vec::new();
+        let mut enc = LengthFramedEncoder::new(&mut out);
+        let big = [0u8; 200];
+        let msgs: [&[u8]; 4] = [&[], &hex!("01 00 00"), &hex!("00 00 00 00 00 00 00"), &big];
+        for msg in msgs {
+            enc.write_frame(msg).unwrap();
+        }
+
+        let mut buf = [0; 256];
+        let mut dec = LengthFramedDecoder::new(&mut buf);
+        let mut frames = frames(&out);
+        for msg in msgs {
+            assert_eq!(dec.decode(frames.next().unwrap()), Ok(msg));
+        }
+        assert_eq!(frames.next(), None);
+
+        // Length doesn't match the message.
+        assert_eq!(
+            dec.decode(&encode(&hex!("09 01"))),
+            Err(DecodeError::LengthMismatch)
+        );
+        assert_eq!(
+            dec.decode(&encode(&hex!("01 01 02"))),
+            Err(DecodeError::LengthMismatch)
+        );
+        // Length prefix overflows.
+        assert_eq!(
+            dec.decode(&encode(&[0xff; 10])),
+            Err(DecodeError::MalformedError)
+        );
+    }
+
+    #[cfg(feature = "acid_io")]
+    #[test]
+    fn acid_io_write() {
+        /// Writer accepting up to `cap` bytes.
+        struct Mock {
+            data: Vec<u8>,
+            cap: usize,
+        }
+
+        impl acid_io::Write for Mock {
+            fn write(&mut self, buf: &[u8]) -> acid_io::Result<usize> {
+                let n = buf.len().min(self.cap - self.data.len());
+                if n == 0 {
+                    return Err(acid_io::ErrorKind::WriteZero.into());
+                }
+                self.data.extend_from_slice(&buf[..n]);
+                Ok(n)
+            }
+
+            fn flush(&mut self) -> acid_io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let data = hex!("01 02 00 03");
+        let mut enc = Encoder::new(AcidIoWrite(Mock {
+            data: Vec::new(),
+            cap: 16,
+        }));
+        enc.write_bytes(&data).unwrap();
+        enc.end().unwrap();
+        assert_eq!(enc.writer().0.data, encode(&data));
+
+        let mut enc = Encoder::new(AcidIoWrite(Mock {
+            data: Vec::new(),
+            cap: 2,
+        }));
+        enc.write_bytes(&data[..2]).unwrap();
+        let err = enc.end().unwrap_err();
+        assert_eq!(err.kind(), acid_io::ErrorKind::WriteZero);
+    }
+
+    type RegressionCase = (&'static [u8], Result<&'static [u8], DecodeError>);
+
+    /// Regression cases, as encoded message and expected [decode_to_slice] result.
+    /// [decode] must agree, with all errors turned into [MalformedError].
+    ///
+    /// Add a case here for each decoding bug found, for example by fuzzing.
+    const REGRESSIONS: &[RegressionCase] = &[
+        // A max run header with no bytes before it.
+        (
+            &hex!("ff"),
+            Err(DecodeError::Truncated {
+                needed: 134,
+                found: 0,
+            }),
+        ),
+        (
+            &hex!("11 ff"),
+            Err(DecodeError::Truncated {
+                needed: 134,
+                found: 1,
+            }),
+        ),
+        // A separator left at the end of the message.
+        (&hex!("01 02 7c 00"), Err(DecodeError::MalformedError)),
+        (&hex!("00"), Err(DecodeError::MalformedError)),
+        // A zero byte used as a literal.
+        (
+            &hex!("01 00 7c"),
+            if cfg!(feature = "strict") {
+                Err(DecodeError::NonCanonical)
+            } else {
+                Ok(&hex!("01 00 00 00 00 00 00"))
+            },
+        ),
+        // A zero group header with all literals present.
+        (&hex!("01 02 7c"), Ok(&hex!("01 02 00 00 00 00 00"))),
+        // A run header right after a zero group.
+        (
+            &hex!("01 02 7c 03 04 05 06 07 08 09 80"),
+            Ok(&hex!("01 02 00 00 00 00 00 03 04 05 06 07 08 09 00")),
+        ),
+    ];
+
+    #[test]
+    fn regressions() {
+        for &(data, expected) in REGRESSIONS {
+            let mut buf = [0; 256];
+            let res = decode_to_slice(data, &mut buf).map(|res| &*res);
+            assert_eq!(res, expected, "decode_to_slice of {:02x?}", data);
+            assert_eq!(
+                decode(data),
+                expected.map(|res| res.to_vec()).map_err(|_| MalformedError),
+                "decode of {:02x?}",
+                data
+            );
+        }
+    }
+
+    #[test]
+    fn debug_padding() {
+        use DecodedByte::*;
+
+        assert_eq!(
+            decode_debug(&encode(&hex!("01 00 02"))),
+            Ok(vec![
+                Data(1),
+                Data(0),
+                Data(2),
+                Padding,
+                Padding,
+                Padding,
+                Padding
+            ])
+        );
+        assert_eq!(
+            decode_debug(&encode(&hex!("01 02 03 04 05 06 07 08"))),
+            Ok([1, 2, 3, 4, 5, 6, 7, 8]
+                .iter()
+                .map(|&b| Data(b))
+                .chain([Padding])
+                .collect())
+        );
+        assert_eq!(
+            decode_debug(&encode(&[0x11; 134])),
+            Ok(vec![Data(0x11); 134])
+        );
+        assert_eq!(decode_debug(&hex!("00")), Err(DecodeError::MalformedError));
+    }
+
+    #[cfg(feature = "genio")]
+    #[test]
+    fn genio_write() {
+        /// Writer accepting up to `cap` bytes.
+        struct Mock {
+            data: Vec<u8>,
+            cap: usize,
+            hint: usize,
+        }
+
+        impl genio::Write for Mock {
+            type WriteError = BufferOverflowError;
+            type FlushError = core::convert::Infallible;
+
+            fn write(&mut self, buf: &[u8]) -> Result<usize, Self::WriteError> {
+                let n = buf.len().min(self.cap - self.data.len());
+                if n == 0 {
+                    return Err(BufferOverflowError);
+                }
+                self.data.extend_from_slice(&buf[..n]);
+                Ok(n)
+            }
+
+            fn flush(&mut self) -> Result<(), Self::FlushError> {
+                Ok(())
+            }
+
+            fn size_hint(&mut self, bytes: usize) {
+                self.hint = bytes;
+            }
+        }
+
+        let data = hex!("01 02 00 03");
+        let mut enc = Encoder::new(GenioWrite(Mock {
+            data: Vec::new(),
+            cap: 16,
+            hint: 0,
+        }));
+        enc.reserve(data.len());
+        enc.write_bytes(&data).unwrap();
+        enc.end().unwrap();
+        assert_eq!(enc.writer().0.data, encode(&data));
+        assert_eq!(enc.writer().0.hint, max_encoded_len(data.len()));
+
+        let mut enc = Encoder::new(GenioWrite(Mock {
+            data: Vec::new(),
+            cap: 2,
+            hint: 0,
+        }));
+        enc.write_bytes(&data[..2]).unwrap();
+        assert_eq!(enc.end(), Err(BufferOverflowError));
+    }
+
+    #[test]
+    fn array() {
+        let data = hex!("01 02 03 04 05 06 07 08 09 00");
+        let enc = encode(&data);
+        assert_eq!(decode_array::<10>(&enc), Ok(data));
+        // The message ends with a zero, so it may be 9 bytes plus padding.
+        assert_eq!(
+            decode_array::<9>(&enc),
+            Ok(hex!("01 02 03 04 05 06 07 08 09"))
+        );
+        assert_eq!(
+            decode_array::<8>(&enc),
+            Err(DecodeError::BufferOverflow { needed: 10 })
+        );
+        assert_eq!(decode_array::<11>(&enc), Err(DecodeError::LengthMismatch));
+    }
+
+    #[test]
+    fn accounted() {
+        let data = hex!("01 00 00 00 00 00 00 02 03 04 05 06 07 08 09");
+        let (res, stats) = encode_accounted(&data);
+        assert_eq!(res, encode(&data));
+        assert_eq!(
+            stats,
+            EncodeStats {
+                header_bytes: 2,
+                literal_bytes: 9,
+                zero_groups: 1,
+            }
+        );
+        assert_eq!(stats.header_bytes + stats.literal_bytes, res.len());
+
+        assert_eq!(encode_accounted(&[]), (vec![], EncodeStats::default()));
+        let (res, stats) = encode_accounted(&[0x11; 300]);
+        assert_eq!((stats.header_bytes, stats.literal_bytes), (3, 300));
+        assert_eq!(res.len(), 303);
+    }
+
+    #[test]
+    fn empty_output() {
+        assert_eq!(decode_to_slice(&[], &mut []), Ok(&mut [][..]));
+        assert_eq!(
+            decode_to_slice(&encode(&hex!("01")), &mut []),
+            Err(DecodeError::BufferOverflow { needed: 7 })
+        );
+        // Malformed messages still get their own error.
+        assert_eq!(
+            decode_to_slice(&hex!("01 ff"), &mut []),
+            Err(DecodeError::Truncated {
+                needed: 134,
+                found: 1
+            })
+        );
+    }
+
+    #[test]
+    fn sender_writer() {
+        let msgs: Vec<Vec<u8>> = (0..20u8).map(|i| vec![i; i as usize * 10]).collect();
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let consumer = std::thread::spawn(move || rx.iter().collect::<Vec<Vec<u8>>>().concat());
+        let mut framer = Framer::new(SenderWriter::new(tx));
+        for msg in &msgs {
+            framer.log_frame(msg).unwrap();
+        }
+        drop(framer);
+
+        let stream = consumer.join().unwrap();
+        let expected: Vec<u8> = msgs.iter().flat_map(|msg| encode_framed(msg)).collect();
+        assert_eq!(stream, expected);
+    }
+
+    #[test]
+    fn sender_writer_closed() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut w = SenderWriter::new(tx);
+        w.write(1).unwrap();
+        w.write(0).unwrap();
+        w.write(2).unwrap();
+        assert_eq!(rx.recv(), Ok(vec![1, 0]));
+
+        drop(rx);
+        assert_eq!(w.write(0), Err(ChannelClosedError));
+        assert_eq!(w.flush(), Err(ChannelClosedError));
+        assert_eq!(w.buf, [2]);
+    }
+
+    #[test]
+    fn range() {
+        let blob = [&b"hdr"[..], &encode(&hex!("01 02"))].concat();
+        let mut buf = [0; 16];
+        assert_eq!(
+            decode_range(&blob, 3..blob.len(), &mut buf).map(|res| res.to_vec()),
+            Ok(hex!("01 02 00 00 00 00 00").to_vec())
+        );
+        assert_eq!(decode_range(&blob, 3..3, &mut buf), Ok(&mut [][..]));
+        #[allow(clippy::reversed_empty_ranges)]
+        for range in [3..blob.len() + 1, 5..4, 10..12] {
+            assert_eq!(
+                decode_range(&blob, range, &mut buf),
+                Err(DecodeError::RangeOutOfBounds)
+            );
+        }
+    }
+
+    #[test]
+    fn max_len_saturates() {
+        assert_eq!(max_encoded_len(usize::MAX), usize::MAX);
+        assert_eq!(max_encoded_len(usize::MAX - 1), usize::MAX);
+        assert_eq!(max_decoded_len(usize::MAX), usize::MAX);
+        assert_eq!(max_decoded_len(usize::MAX / 7), usize::MAX / 7 * 7);
+        assert_eq!(max_decoded_len(usize::MAX / 7 + 1), usize::MAX);
+
+        // A ratio limit that saturates is the same as no limit.
+        let data = encode(&[0; 70]);
+        assert_eq!(decode_ratio_limited(&data, usize::MAX), Ok(vec![0; 70]));
+
+        let mut w = StatsWriter::new(Vec::new());
+        w.stats.bytes = usize::MAX;
+        w.write(0).unwrap();
+        assert_eq!(w.stats().bytes, 0);
+    }
+
+    #[test]
+    fn accumulator_reset() {
+        let mut acc = FrameAccumulator::new(16);
+        for &b in &encode(&hex!("01 02 03"))[..2] {
+            assert_eq!(acc.push(b), None);
+        }
+        acc.reset();
+        let mut res = Vec::new();
+        for b in encode_framed(&hex!("04 05")) {
+            res.extend(acc.push(b));
+        }
+        assert_eq!(res, [Ok(hex!("04 05 00 00 00 00 00").to_vec())]);
+
+        // Also stops discarding an overlong message.
+        for _ in 0..17 {
+            acc.push(1);
+        }
+        acc.reset();
+        let mut res = Vec::new();
+        for b in encode_framed(&hex!("06")) {
+            res.extend(acc.push(b));
+        }
+        assert_eq!(res, [Ok(hex!("06 00 00 00 00 00 00").to_vec())]);
+    }
+
+    #[test]
+    fn defmt_indexed() {
+        let stream = [
+            &hex!("00")[..],
+            &encode_framed(&hex!("01")),
+            &hex!("00 00"),
+            &hex!("01 ff 00"),
+            &encode_framed(&hex!("02 03")),
+        ]
+        .concat();
+        let res: Vec<_> = defmt_frames_indexed(&stream).collect();
+        assert_eq!(
+            res,
+            [
+                (0, Ok(hex!("01 00 00 00 00 00 00").to_vec())),
+                (
+                    1,
+                    Err(DecodeError::Truncated {
+                        needed: 134,
+                        found: 1
+                    })
+                ),
+                (2, Ok(hex!("02 03 00 00 00 00 00").to_vec())),
+            ]
+        );
+    }
+
+    #[test]
+    fn ring_decoder() {
+        let mut out = [0; max_decoded_len(16)];
+        let mut dec = RingDecoder::<16>::new(&mut out);
+        let mut results = Vec::new();
+        let stream = [
+            &encode_framed(&hex!("01 02"))[..],
+            &hex!("00"),
+            &[0x11; 17],
+            &hex!("00"),
+            &encode_framed(&hex!("03")),
+            &hex!("01 ff 00"),
+            &encode_framed(&[0x22; 15]),
+        ]
+        .concat();
+        for b in stream {
+            results.extend(dec.push(b).map(|res| res.map(|msg| msg.to_vec())));
+        }
+        assert_eq!(
+            results,
+            [
+                Ok(hex!("01 02 00 00 00 00 00").to_vec()),
+                Err(DecodeError::FrameTooLong),
+                Ok(hex!("03 00 00 00 00 00 00").to_vec()),
+                Err(DecodeError::Truncated {
+                    needed: 134,
+                    found: 1
+                }),
+                Ok([&[0x22; 15][..], &[0]].concat()),
+            ]
+        );
+
+        // Reset mid-message.
+        dec.push(0x33);
+        dec.reset();
+        let mut last = None;
+        for b in encode_framed(&hex!("04")) {
+            last = dec.push(b).map(|res| res.map(|msg| msg.to_vec()));
+        }
+        assert_eq!(last, Some(Ok(hex!("04 00 00 00 00 00 00").to_vec())));
+
+        let mut out = [0; 4];
+        let mut dec = RingDecoder::<16>::new(&mut out);
+        let res = encode_framed(&hex!("05"))
+            .into_iter()
+            .find_map(|b| dec.push(b).map(|res| res.map(|msg| msg.to_vec())));
+        assert_eq!(res, Some(Err(DecodeError::BufferOverflow { needed: 7 })));
+    }
+
+    #[test]
+    fn params() {
+        let params = format_params();
+        assert_eq!(FORMAT_VERSION, 1);
+        assert_eq!(header_len(0x7f), Some((0, params.group_size)));
+        assert_eq!(header_len(0xff), Some((params.max_run, params.max_run)));
+        assert_eq!(header_len(params.terminator), None);
+        assert_eq!(encode_framed(&[]), [params.terminator]);
+    }
+
+    #[test]
+    fn uninit() {
+        let data = hex!("01 02 00 03 04 05 06 07 08 09 0a");
+        let enc = encode(&data);
+        let mut buf = [MaybeUninit::uninit(); 32];
+        let res = decode_to_uninit(&enc, &mut buf).unwrap();
+        assert_eq!(res, &[&data[..], &[0; 3]].concat()[..]);
+
+        let mut buf = [MaybeUninit::uninit(); 13];
+        assert_eq!(
+            decode_to_uninit(&enc, &mut buf),
+            Err(DecodeError::BufferOverflow { needed: 14 })
+        );
+        assert_eq!(decode_to_uninit(&[], &mut []), Ok(&mut [][..]));
+        assert_eq!(
+            decode_to_uninit(&hex!("01 ff"), &mut buf),
+            Err(DecodeError::Truncated {
+                needed: 134,
+                found: 1
+            })
+        );
+    }
+
+    #[test]
+    fn frame_in_progress() {
+        let mut enc = Encoder::new(Vec::new());
+        for msg in [&hex!("01 02")[..], &hex!("03 00 04")] {
+            let mut frame = enc.start_frame();
+            frame.write_bytes(msg).unwrap();
+            let (e, res) = frame.end();
+            res.unwrap();
+            enc = e;
+        }
+        assert_eq!(
+            enc.writer(),
+            &[encode(&hex!("01 02")), encode(&hex!("03 00 04"))].concat()
+        );
+
+        let mut buf = [0; 2];
+        let mut frame = Encoder::new(SliceWriter::new(&mut buf)).start_frame();
+        frame.write_bytes(&hex!("01 02")).unwrap();
+        let (enc, res) = frame.end();
+        assert_eq!(res, Err(BufferOverflowError));
+        assert!(!enc.in_message());
+    }
+
+    #[test]
+    fn verify() {
+        let good = encode_batch(&[&hex!("01"), &hex!("02 00 03"), &[0x11; 200]]).0;
+        assert_eq!(verify_all(&good), Ok(3));
+        assert_eq!(verify_all(&hex!("00 00")), Ok(0));
+
+        let bad = [&good[..], &hex!("00 01 ff 00"), &good].concat();
+        assert_eq!(
+            verify_all(&bad),
+            Err((
+                3,
+                DecodeError::Truncated {
+                    needed: 134,
+                    found: 1
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn decode_strategies() {
+        let mut rand = rng(0x2545_f491_4f6c_dd1d);
+        let data: Vec<u8> = (0..10_000)
+            .map(|_| match rand() % 4 {
+                0 => 0,
+                _ => (rand() >> 8) as u8,
+            })
+            .collect();
+        assert!(data.iter().filter(|&&b| b != 0).count() > 5000);
+        let (enc, stats) = encode_accounted(&data);
+        // Some groups are literal runs.
+        assert!(stats.zero_groups < stats.header_bytes);
+
+        let mut bufs = [vec![0; 11_000], vec![0; 11_000]];
+        let [front, back] = &mut bufs;
+        let front = decode_to_slice_with(&enc, front, DecodeStrategy::FrontFill).unwrap();
+        let back = decode_to_slice_with(&enc, back, DecodeStrategy::BackFill).unwrap();
+        assert_eq!(front, back);
+        assert_eq!(front[..data.len()], data);
+
+        for strategy in [DecodeStrategy::FrontFill, DecodeStrategy::BackFill] {
+            assert_eq!(
+                decode_to_slice_with(&enc, &mut [0; 100], strategy),
+                Err(DecodeError::BufferOverflow {
+                    needed: front.len()
+                })
+            );
+            assert_eq!(
+                decode_to_slice_with(&hex!("01 ff"), &mut [0; 100], strategy),
+                Err(DecodeError::Truncated {
+                    needed: 134,
+                    found: 1
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn single_byte() {
+        for x in 0..=255u8 {
+            let enc = encode(&[x]);
+            match x {
+                0 => assert_eq!(enc, [0x7f]),
+                _ => assert_eq!(enc, [x, 0x7e]),
+            }
+            let mut expected = [0; 7];
+            expected[0] = x;
+            assert_eq!(decode(&enc), Ok(expected.to_vec()), "byte {:02x}", x);
+        }
+    }
+
+    #[cfg(feature = "allocator_api")]
+    #[test]
+    fn decode_in_allocator() {
+        let data = hex!("01 02 00 03 04 05 06 07 08 09 0a");
+        let enc = encode(&data);
+        let res = decode_in(&enc, std::alloc::Global).unwrap();
+        assert_eq!(&res[..], &decode(&enc).unwrap()[..]);
+        assert_eq!(
+            decode_in(&hex!("01 ff"), std::alloc::Global),
+            Err(DecodeError::Truncated {
+                needed: 134,
+                found: 1
+            })
+        );
+    }
+
+    #[test]
+    fn repeated() {
+        for count in [0, 1, 6, 7, 8, 14, 133, 134, 135, 140, 268, 269, 1000] {
+            for byte in [0x00, 0x01, 0xff] {
+                assert_eq!(
+                    encode_repeated(byte, count),
+                    encode(&vec![byte; count]),
+                    "{} times {:02x}",
+                    count,
+                    byte
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn slice_trimmed() {
+        let mut buf = [0; 256];
+        for len in 0..=140 {
+            let data = vec![0x11; len];
+            let (res, padding) = decode_to_slice_trimmed(&encode(&data), &mut buf).unwrap();
+            // Runs of 134 bytes need no padding, then the last group is padded.
+            let expected = match len % 134 {
+                0 => 0,
+                n @ 1..=6 => 7 - n,
+                _ => 1,
+            };
+            assert_eq!(padding, expected, "length {}", len);
+            assert_eq!(res[..res.len() - padding], data[..]);
+        }
+
+        // Trailing zeros of the message count too.
+        let (_, padding) = decode_to_slice_trimmed(&encode(&hex!("01 00 00")), &mut buf).unwrap();
+        assert_eq!(padding, 6);
+    }
+
+    #[test]
+    fn from_seek() {
+        let mut rand = rng(0x2545_f491_4f6c_dd1d);
+        for (len, zeros) in [(0, 0), (10, 2), (300_000, 0), (300_000, 3), (300_000, 100)] {
+            let data: Vec<u8> = (0..len)
+                .map(|_| {
+                    if rand() % 100 < zeros {
+                        0
+                    } else {
+                        (rand() >> 8) as u8 | 1
+                    }
+                })
+                .collect();
+            let enc = encode(&data);
+            let stream = [&enc[..], &[0, 1, 2]].concat();
+
+            let mut out = Vec::new();
+            decode_from_seek(
+                &mut std::io::Cursor::new(&stream),
+                enc.len() as u64,
+                &mut out,
+            )
+            .unwrap();
+            assert_eq!(out, decode(&enc).unwrap());
+        }
+
+        let mut out = Vec::new();
+        let err =
+            decode_from_seek(&mut std::io::Cursor::new(hex!("01 ff")), 2, &mut out).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn decode_both_orders() {
+        let data = hex!("01 02 03 04 05 06 07 08 09 0a");
+        let enc = encode(&data);
+        let mut rev = enc.clone();
+        rev.reverse();
+        // Reversed, the header ends up first, so the message doesn't decode.
+        assert!(decode(&rev).is_err());
+
+        assert_eq!(try_decode_both(&enc), decode_vec(&enc));
+        assert_eq!(try_decode_both(&rev), decode_vec(&enc));
+        assert_eq!(
+            try_decode_both(&hex!("01 ff 02")),
+            Err(DecodeError::Truncated {
+                needed: 6,
+                found: 2
+            })
+        );
+    }
+
+    #[test]
+    fn crc_tee() {
+        fn crc32(data: &[u8]) -> u32 {
+            let mut crc = !0u32;
+            for &b in data {
+                crc ^= b as u32;
+                for _ in 0..8 {
+                    crc = (crc >> 1) ^ (0xedb8_8320 & (crc & 1).wrapping_neg());
+                }
+            }
+            !crc
+        }
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+
+        let mut w = CrcTeeWriter::new(Vec::new());
+        for &b in b"123456789" {
+            w.write(b).unwrap();
+        }
+        assert_eq!(w.crc(), 0xcbf4_3926);
+        w.reset();
+        assert_eq!(w.crc(), 0);
+
+        let data = hex!("01 02 00 03 04 05 06 07 08 09 0a");
+        let mut enc = Encoder::new(CrcTeeWriter::new(Vec::new()));
+        enc.write_bytes(&data).unwrap();
+        enc.end().unwrap();
+        let crc = enc.writer().crc();
+        assert_eq!(crc, crc32(&encode(&data)));
+
+        fn crc16(data: &[u8]) -> u16 {
+            let mut crc = 0xffffu16;
+            for &b in data {
+                crc ^= (b as u16) << 8;
+                for _ in 0..8 {
+                    crc = (crc << 1) ^ (0x1021 & (crc >> 15).wrapping_neg());
+                }
+            }
+            crc
+        }
+        assert_eq!(crc16(b"123456789"), 0x29b1);
+
+        let mut w = CrcTeeWriter::with_crc(Vec::new(), Crc16);
+        for &b in b"123456789" {
+            w.write(b).unwrap();
+        }
+        assert_eq!(w.crc(), 0x29b1);
+        w.reset();
+        assert_eq!(w.crc(), 0xffff);
+
+        let mut enc = Encoder::new(CrcTeeWriter::with_crc(Vec::new(), Crc16));
+        enc.write_bytes(&data).unwrap();
+        enc.end().unwrap();
+        let crc = enc.writer().crc();
+        assert_eq!(crc, crc16(&encode(&data)));
+    }
+
+    #[test]
+    fn capped() {
+        // 3 bytes, decoding to 7 with padding.
+        let enc = encode(&hex!("01 02 03"));
+        let full = hex!("01 02 03 00 00 00 00");
+
+        assert_eq!(decode_capped(&enc, 100), Ok(full.to_vec()));
+        for cap in 3..=7 {
+            assert_eq!(decode_capped(&enc, cap), Ok(full[..cap].to_vec()));
+        }
+        assert_eq!(
+            decode_capped(&enc, 2),
+            Err(DecodeError::BufferOverflow { needed: 7 })
+        );
+        assert_eq!(decode_capped(&[], 0), Ok(vec![]));
+    }
+
+    #[test]
+    fn chunked_frames() {
+        let big: Vec<u8> = (0..2000).map(|i| i as u8).collect();
+        let msgs: [&[u8]; 4] = [&hex!("01 02"), &[], &big, &[0x11; 7]];
+
+        let mut packets: Vec<Vec<u8>> = Vec::new();
+        let mut framer = Framer::new(ChunkedFrameWriter::<_, 16>::new(|p: &[u8]| {
+            packets.push(p.to_vec());
+            Ok::<_, ()>(())
+        }));
+        for msg in msgs {
+            framer.log_frame(msg).unwrap();
+        }
+        assert!(packets.iter().all(|p| p.len() <= 16 && p[0] != 0));
+        // The big message needs more than 64 packets, so sequence numbers wrap.
+        assert!(packets.len() > 64);
+
+        let mut rx = FrameReassembler::new(4096);
+        let res: Vec<_> = packets.iter().filter_map(|p| rx.push(p)).collect();
+        let expected: Vec<_> = msgs.iter().map(|msg| Ok(encode(msg))).collect();
+        assert_eq!(res, expected);
+
+        // Drop a packet in the middle of the big message.
+        let mut lost = packets.clone();
+        lost.remove(10);
+        let res: Vec<_> = lost.iter().filter_map(|p| rx.push(p)).collect();
+        assert_eq!(
+            res,
+            [
+                Ok(encode(msgs[0])),
+                Ok(encode(msgs[1])),
+                Err(DecodeError::PacketLost),
+                Ok(encode(msgs[3])),
+            ]
+        );
+
+        let mut rx = FrameReassembler::new(100);
+        let res: Vec<_> = packets.iter().filter_map(|p| rx.push(p)).collect();
+        assert_eq!(res[2], Err(DecodeError::FrameTooLong));
+        assert_eq!(res[3], Ok(encode(msgs[3])));
+    }
+
+    #[test]
+    fn writer_windowed() {
+        let data: Vec<u8> = (0..1000)
+            .map(|i| [i as u8, 0][(i % 3 == 0) as usize])
+            .collect();
+        let enc = encode(&[&data[..], &[0x11; 300]].concat());
+        let expected = decode(&enc).unwrap();
+
+        fn check<const WINDOW: usize>(enc: &[u8], expected: &[u8]) {
+            let mut out = Vec::new();
+            decode_to_writer_windowed::<_, WINDOW>(enc, &mut out).unwrap();
+            assert_eq!(out, expected, "window {}", WINDOW);
+        }
+        check::<0>(&enc, &expected);
+        check::<1>(&enc, &expected);
+        check::<7>(&enc, &expected);
+        check::<134>(&enc, &expected);
+        check::<100>(&enc, &expected);
+        check::<4096>(&enc, &expected);
+        check::<16>(&[], &[]);
+
+        let mut out = Vec::new();
+        assert_eq!(
+            decode_to_writer_windowed::<_, 16>(&hex!("01 ff"), &mut out),
+            Err(DecodeWriteError::Decode(DecodeError::Truncated {
+                needed: 134,
+                found: 1
+            }))
+        );
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn accumulator_status() {
+        let msg = hex!("11 00 00 22 33 00 00 00 44");
+        let mut acc = FrameAccumulator::new(64);
+        assert!(!acc.is_incomplete());
+        for &b in &encode(&msg) {
+            assert_eq!(acc.push_status(b), FrameStatus::Incomplete);
+            assert!(acc.is_incomplete());
+        }
+        assert_eq!(
+            acc.push_status(0),
+            FrameStatus::Complete(decode(&encode(&msg)).unwrap())
+        );
+        assert!(!acc.is_incomplete());
+
+        // Empty messages are skipped.
+        assert_eq!(acc.push_status(0), FrameStatus::Incomplete);
+
+        assert_eq!(acc.push_status(0x01), FrameStatus::Incomplete);
+        assert_eq!(acc.push_status(0xff), FrameStatus::Incomplete);
+        assert!(matches!(
+            acc.push_status(0),
+            FrameStatus::Err(DecodeError::Truncated { .. })
+        ));
+
+        let mut acc = FrameAccumulator::new(2);
+        assert_eq!(acc.push_status(0x11), FrameStatus::Incomplete);
+        assert_eq!(acc.push_status(0x22), FrameStatus::Incomplete);
+        assert_eq!(
+            acc.push_status(0x33),
+            FrameStatus::Err(DecodeError::FrameTooLong)
+        );
+        assert_eq!(acc.push_status(0x44), FrameStatus::Incomplete);
+        assert!(!acc.is_incomplete());
+        assert_eq!(acc.push_status(0), FrameStatus::Incomplete);
+    }
+
+    #[test]
+    fn with_hint() {
+        for (len, hint) in [
+            (0, 1),
+            (1, 2),
+            (2, 3),
+            (127, 8),
+            (128, 9),
+            (255, 9),
+            (256, 10),
+        ] {
+            let msg: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let enc = encode_with_hint(&msg);
+            assert_eq!(enc[0], hint);
+            assert_eq!(&enc[1..], &encode(&msg)[..]);
+            assert!(len <= hint_capacity(hint));
+            assert!(len == 0 || len > hint_capacity(hint - 1));
+            assert_eq!(decode_with_hint(&enc).unwrap(), decode(&enc[1..]).unwrap());
+        }
+        assert_eq!(hint_capacity(0), 0);
+        assert_eq!(hint_capacity(usize::BITS as u8 + 1), usize::MAX);
+        assert_eq!(hint_capacity(0xff), usize::MAX);
+        assert_eq!(decode_with_hint(&[]), Err(DecodeError::MalformedError));
+    }
+
+    #[test]
+    fn zero_group_bit_order() {
+        for x in 0x01..=0x7fu8 {
+            // Bit i of the header is set when byte i of the group is zero.
+            let mut data = Vec::new();
+            let mut expected = [0; 7];
+            for (i, b) in expected.iter_mut().enumerate() {
+                if x & (1 << i) == 0 {
+                    *b = 0x11 * (i as u8 + 1);
+                    data.push(*b);
+                }
+            }
+            data.push(x);
+
+            assert_eq!(decode(&data).unwrap(), expected, "header {:02x}", x);
+            let mut buf = [0xaa; 7];
+            assert_eq!(decode_to_slice(&data, &mut buf).unwrap(), expected);
+            let mut out = Vec::new();
+            decode_to_writer_windowed::<_, 3>(&data, &mut out).unwrap();
+            assert_eq!(out, expected);
+            let mut fwd = Vec::new();
+            decode_forward_with(&data, |b| fwd.push(b)).unwrap();
+            assert_eq!(fwd, expected);
+
+            assert_eq!(encode(&expected), data);
+        }
+        assert_eq!(decode(&hex!("7f")).unwrap(), [0; 7]);
+    }
+
+    #[test]
+    fn frame_pool() {
+        let mut pool = FramePool::with_capacity(300);
+        let cap = pool.capacity();
+        let long: Vec<u8> = (0..300).map(|i| i as u8).collect();
+        for msg in [&hex!("11 00 22")[..], &[], &long, &hex!("00")] {
+            assert_eq!(pool.encode(msg), &encode_framed(msg)[..]);
+        }
+        assert_eq!(pool.capacity(), cap);
+
+        let mut pool = FramePool::new();
+        let mut stream = Vec::new();
+        for msg in [&hex!("11 00 22")[..], &hex!("33")] {
+            stream.extend_from_slice(pool.encode(msg));
         }
+        assert_eq!(stream, hex!("11 22 7a 00 33 7e 00"));
     }
 }